@@ -0,0 +1,309 @@
+use std::collections::HashMap;
+use crate::bytecode::{Chunk, Function, Instruction, Program};
+use crate::syntax_tree::{AbstractSyntaxTree, AssignmentExpression, ASTStatement, ASTStatementType, BinaryExpression, BinaryOperatorType, BooleanExpression, ASTLetStatement, ASTBlockStatement, ASTIfStatement, ASTWhileStatement, ASTFunctionDeclaration, ASTReturnStatement, CallExpression, DoubleExpression, InterpolationExpression, InterpolationPart, NumberExpression, StringExpression, UnaryExpression, UnaryOperatorType, VariableExpression, ASTTraverser};
+use crate::syntax_tree::evaluator::BuiltinFunction;
+use crate::syntax_tree::lexer::{SourceCodeSpan, Token};
+use crate::syntax_tree::scopes::ScopeStack;
+use crate::syntax_tree::value::Value;
+
+struct LoopContext {
+    start: usize,
+    //Jumps emitted by break statements, patched once the end of the loop is known
+    breaks: Vec<usize>,
+}
+
+//Lowers a checked tree into bytecode, variables are resolved to slots at compile time so the VM never looks up names
+pub struct BytecodeCompiler {
+    chunk: Chunk,
+    //Local slots of the function being compiled, its global scope stays empty as top level variables are globals
+    locals: ScopeStack<usize>,
+    local_count: usize,
+    globals: HashMap<String, usize>,
+    function_indices: HashMap<String, usize>,
+    functions: Vec<Option<Function>>,
+    loops: Vec<LoopContext>,
+}
+
+impl BytecodeCompiler {
+    pub fn new() -> Self {
+        Self {
+            chunk: Chunk::new(),
+            locals: ScopeStack::new(),
+            local_count: 0,
+            globals: HashMap::new(),
+            function_indices: HashMap::new(),
+            functions: Vec::new(),
+            loops: Vec::new(),
+        }
+    }
+
+    pub fn compile(mut self, ast: &AbstractSyntaxTree) -> Program {
+        //Functions are hoisted so calls can refer to them before their bodies are compiled
+        for function in ast.functions() {
+            if !self.function_indices.contains_key(function.identifier()) {
+                self.function_indices.insert(function.identifier().to_string(), self.functions.len());
+                self.functions.push(None);
+            }
+        }
+        ast.visit(&mut self);
+        let main = Function {
+            name: String::new(),
+            arity: 0,
+            local_count: self.local_count,
+            chunk: self.chunk,
+        };
+        return Program {
+            main,
+            functions: self.functions.into_iter().map(|function| function.expect("Every hoisted function is compiled")).collect(),
+            global_count: self.globals.len(),
+        };
+    }
+
+    fn emit(&mut self, instruction: Instruction, span: Option<&SourceCodeSpan>) -> usize {
+        return self.chunk.emit(instruction, span);
+    }
+
+    fn emit_constant(&mut self, value: Value) {
+        let index = self.chunk.add_constant(value);
+        self.emit(Instruction::CONSTANT(index), None);
+    }
+
+    fn patch_jump_here(&mut self, jump: usize) {
+        let target = self.chunk.code.len();
+        self.chunk.patch_jump(jump, target);
+    }
+
+    fn declare_variable(&mut self, identifier: &Token) {
+        let name = identifier.span.literal.clone();
+        if self.locals.is_global() {
+            let slot = self.global_slot(&name);
+            self.emit(Instruction::STOREGLOBAL(slot), Some(&identifier.span));
+            return;
+        }
+        let slot = self.local_count;
+        self.local_count += 1;
+        self.locals.declare(name, slot);
+        self.emit(Instruction::STORELOCAL(slot), Some(&identifier.span));
+    }
+
+    fn global_slot(&mut self, name: &str) -> usize {
+        let slot = self.globals.len();
+        return *self.globals.entry(name.to_string()).or_insert(slot);
+    }
+
+    fn load_variable(&mut self, identifier: &Token) {
+        let instruction = match self.locals.lookup(&identifier.span.literal) {
+            Some(&slot) => Instruction::LOADLOCAL(slot),
+            None => Instruction::LOADGLOBAL(self.global_slot(&identifier.span.literal)),
+        };
+        self.emit(instruction, Some(&identifier.span));
+    }
+
+    //Both operands of a logical operator must be booleans, the right one is skipped once the left one decides the result
+    fn compile_logical_expression(&mut self, binary_expression: &BinaryExpression) {
+        let span = &binary_expression.operator.token.span;
+        let is_and = binary_expression.operator.kind == BinaryOperatorType::LOGICALAND;
+        let short_circuit = |target| if is_and { Instruction::JUMPIFFALSE(target) } else { Instruction::JUMPIFTRUE(target) };
+        self.goto_expression(&binary_expression.left);
+        let left_jump = self.emit(short_circuit(0), Some(span));
+        self.goto_expression(&binary_expression.right);
+        let right_jump = self.emit(short_circuit(0), Some(span));
+        self.emit_constant(Value::BOOLEAN(is_and));
+        let end_jump = self.emit(Instruction::JUMP(0), None);
+        self.patch_jump_here(left_jump);
+        self.patch_jump_here(right_jump);
+        self.emit_constant(Value::BOOLEAN(!is_and));
+        self.patch_jump_here(end_jump);
+    }
+}
+
+impl ASTTraverser for BytecodeCompiler {
+    fn goto_statement(&mut self, statement: &ASTStatement) {
+        self.do_visit_statement(statement);
+        if let ASTStatementType::EXPRESSION(_) = statement.kind {
+            self.emit(Instruction::POP, None);
+        }
+    }
+
+    fn goto_let_statement(&mut self, let_statement: &ASTLetStatement) {
+        //The initializer still sees the variable being shadowed
+        self.goto_expression(&let_statement.initializer);
+        self.declare_variable(&let_statement.identifier);
+    }
+
+    fn goto_block_statement(&mut self, block_statement: &ASTBlockStatement) {
+        self.locals.enter_scope();
+        for statement in &block_statement.statements {
+            self.goto_statement(statement);
+        }
+        self.locals.exit_scope();
+    }
+
+    fn goto_if_statement(&mut self, if_statement: &ASTIfStatement) {
+        self.goto_expression(&if_statement.condition);
+        let else_jump = self.emit(Instruction::JUMPIFFALSE(0), Some(&if_statement.if_keyword.span));
+        self.goto_statement(&if_statement.then_branch);
+        match &if_statement.else_branch {
+            Some(else_branch) => {
+                let end_jump = self.emit(Instruction::JUMP(0), None);
+                self.patch_jump_here(else_jump);
+                self.goto_statement(&else_branch.else_statement);
+                self.patch_jump_here(end_jump);
+            }
+            None => self.patch_jump_here(else_jump),
+        }
+    }
+
+    fn goto_while_statement(&mut self, while_statement: &ASTWhileStatement) {
+        let start = self.chunk.code.len();
+        self.goto_expression(&while_statement.condition);
+        let exit_jump = self.emit(Instruction::JUMPIFFALSE(0), Some(&while_statement.while_keyword.span));
+        self.loops.push(LoopContext { start, breaks: Vec::new() });
+        self.goto_statement(&while_statement.body);
+        self.emit(Instruction::JUMP(start), None);
+        let loop_context = self.loops.pop().unwrap();
+        self.patch_jump_here(exit_jump);
+        for break_jump in loop_context.breaks {
+            self.patch_jump_here(break_jump);
+        }
+    }
+
+    fn goto_break_statement(&mut self, break_keyword: &Token) {
+        let jump = self.emit(Instruction::JUMP(0), None);
+        self.loops.last_mut().expect("Break outside of a loop").breaks.push(jump);
+    }
+
+    fn goto_continue_statement(&mut self, continue_keyword: &Token) {
+        let start = self.loops.last().expect("Continue outside of a loop").start;
+        self.emit(Instruction::JUMP(start), None);
+    }
+
+    fn goto_function_declaration(&mut self, function_declaration: &ASTFunctionDeclaration) {
+        let enclosing_chunk = std::mem::replace(&mut self.chunk, Chunk::new());
+        let enclosing_local_count = std::mem::replace(&mut self.local_count, 0);
+        let enclosing_loops = std::mem::take(&mut self.loops);
+        let enclosing_locals = self.locals.take_locals();
+
+        self.locals.enter_scope();
+        for parameter in &function_declaration.parameters {
+            self.locals.declare(parameter.identifier.span.literal.clone(), self.local_count);
+            self.local_count += 1;
+        }
+        self.goto_statement(&function_declaration.body);
+        //Falling off the end of the body returns nothing
+        self.emit(Instruction::RETURNNONE, None);
+        let function = Function {
+            name: function_declaration.identifier().to_string(),
+            arity: function_declaration.parameters.len(),
+            local_count: self.local_count,
+            chunk: std::mem::replace(&mut self.chunk, enclosing_chunk),
+        };
+
+        self.locals.restore_locals(enclosing_locals);
+        self.loops = enclosing_loops;
+        self.local_count = enclosing_local_count;
+        let index = self.function_indices[function_declaration.identifier()];
+        self.functions[index] = Some(function);
+    }
+
+    fn goto_return_statement(&mut self, return_statement: &ASTReturnStatement) {
+        match &return_statement.return_value {
+            Some(return_value) => {
+                self.goto_expression(return_value);
+                self.emit(Instruction::RETURN, Some(&return_statement.return_keyword.span));
+            }
+            None => {
+                self.emit(Instruction::RETURNNONE, None);
+            }
+        }
+    }
+
+    fn goto_call_expression(&mut self, call_expression: &CallExpression) {
+        for argument in &call_expression.arguments {
+            self.goto_expression(argument);
+        }
+        if let Some(builtin) = BuiltinFunction::from_name(call_expression.identifier()) {
+            self.emit(Instruction::CALLBUILTIN(builtin, call_expression.arguments.len()), Some(&call_expression.identifier.span));
+            return;
+        }
+        let index = self.function_indices[call_expression.identifier()];
+        self.emit(Instruction::CALL(index, call_expression.arguments.len()), Some(&call_expression.identifier.span));
+    }
+
+    fn goto_variable_expression(&mut self, variable_expression: &VariableExpression) {
+        self.load_variable(&variable_expression.identifier);
+    }
+
+    //Stores the new value and loads it again, as the assignment itself evaluates to it
+    fn goto_assignment_expression(&mut self, assignment_expression: &AssignmentExpression) {
+        let span = Some(&assignment_expression.operator_token.span);
+        if let Some(operator) = assignment_expression.operator {
+            self.load_variable(&assignment_expression.identifier);
+            self.goto_expression(&assignment_expression.value);
+            self.emit(Instruction::BINARY(operator), span);
+        } else {
+            self.goto_expression(&assignment_expression.value);
+        }
+        let instruction = match self.locals.lookup(assignment_expression.identifier()) {
+            Some(&slot) => Instruction::STORELOCAL(slot),
+            None => Instruction::STOREGLOBAL(self.global_slot(assignment_expression.identifier())),
+        };
+        self.emit(instruction, span);
+        self.load_variable(&assignment_expression.identifier);
+    }
+
+    fn goto_number_expression(&mut self, number: &NumberExpression) {
+        self.emit_constant(Value::INTEGER(number.number));
+    }
+
+    fn goto_double_expression(&mut self, double: &DoubleExpression) {
+        self.emit_constant(Value::DOUBLE(double.value));
+    }
+
+    fn goto_boolean_expression(&mut self, boolean: &BooleanExpression) {
+        self.emit_constant(Value::BOOLEAN(boolean.value));
+    }
+
+    fn goto_string_expression(&mut self, string: &StringExpression) {
+        self.emit_constant(Value::STRING(string.value.clone()));
+    }
+
+    fn goto_interpolation_expression(&mut self, interpolation: &InterpolationExpression) {
+        for part in &interpolation.parts {
+            match part {
+                InterpolationPart::TEXT(text) => self.emit_constant(Value::STRING(text.clone())),
+                InterpolationPart::EXPRESSION(expression, span) => {
+                    self.goto_expression(expression);
+                    self.emit(Instruction::TOSTRING, Some(span));
+                }
+            }
+        }
+        self.emit(Instruction::CONCAT(interpolation.parts.len()), None);
+    }
+
+    fn goto_error(&mut self, span: &SourceCodeSpan) {
+        self.emit(Instruction::INVALID, Some(span));
+    }
+
+    fn goto_unary_expression(&mut self, unary_expression: &UnaryExpression) {
+        self.goto_expression(&unary_expression.operand);
+        let instruction = match unary_expression.operator.kind {
+            UnaryOperatorType::MINUS => Instruction::NEGATE,
+            UnaryOperatorType::NOT => Instruction::NOT,
+            UnaryOperatorType::LOGICALNOT => Instruction::LOGICALNOT,
+        };
+        self.emit(instruction, Some(&unary_expression.operator.token.span));
+    }
+
+    fn goto_binary_expression(&mut self, binary_expression: &BinaryExpression) {
+        let instruction = match binary_expression.operator.kind {
+            BinaryOperatorType::LOGICALAND | BinaryOperatorType::LOGICALOR => return self.compile_logical_expression(binary_expression),
+            BinaryOperatorType::EQUALS => Instruction::EQUAL,
+            BinaryOperatorType::NOTEQUALS => Instruction::NOTEQUAL,
+            operator => Instruction::BINARY(operator),
+        };
+        self.goto_expression(&binary_expression.left);
+        self.goto_expression(&binary_expression.right);
+        self.emit(instruction, Some(&binary_expression.operator.token.span));
+    }
+}
@@ -0,0 +1,99 @@
+use crate::syntax_tree::BinaryOperatorType;
+use crate::syntax_tree::evaluator::BuiltinFunction;
+use crate::syntax_tree::lexer::SourceCodeSpan;
+use crate::syntax_tree::value::Value;
+
+pub mod compiler;
+pub mod vm;
+
+//Stack slots hold no value after calls to functions that did not return anything, every instruction
+//that needs an operand reports that at its span
+#[derive(Debug, PartialEq, Clone)]
+pub enum Instruction {
+    CONSTANT(usize),
+    //Discards the result of an expression statement
+    POP,
+    LOADLOCAL(usize),
+    STORELOCAL(usize),
+    LOADGLOBAL(usize),
+    STOREGLOBAL(usize),
+    NEGATE,
+    NOT,
+    LOGICALNOT,
+    //Integer operations shared with the evaluator through evaluate_integer_operation
+    BINARY(BinaryOperatorType),
+    EQUAL,
+    NOTEQUAL,
+    JUMP(usize),
+    //Conditional jumps pop a boolean condition
+    JUMPIFFALSE(usize),
+    JUMPIFTRUE(usize),
+    //Function index and number of arguments
+    CALL(usize, usize),
+    //Builtin and number of arguments, builtins do not return anything
+    CALLBUILTIN(BuiltinFunction, usize),
+    //Converts the value of an interpolated expression to its text
+    TOSTRING,
+    //Joins the given number of strings into one
+    CONCAT(usize),
+    RETURN,
+    RETURNNONE,
+    INVALID,
+}
+
+pub struct Chunk {
+    pub code: Vec<Instruction>,
+    //Source location of every instruction, used to report runtime errors
+    pub spans: Vec<Option<SourceCodeSpan>>,
+    pub constants: Vec<Value>,
+}
+
+impl Chunk {
+    pub fn new() -> Self {
+        Self { code: Vec::new(), spans: Vec::new(), constants: Vec::new() }
+    }
+
+    pub fn emit(&mut self, instruction: Instruction, span: Option<&SourceCodeSpan>) -> usize {
+        self.code.push(instruction);
+        self.spans.push(span.cloned());
+        return self.code.len() - 1;
+    }
+
+    pub fn add_constant(&mut self, value: Value) -> usize {
+        if let Some(index) = self.constants.iter().position(|constant| Self::is_same_constant(constant, &value)) {
+            return index;
+        }
+        self.constants.push(value);
+        return self.constants.len() - 1;
+    }
+
+    //Doubles are compared bit by bit, 0.0 and -0.0 are equal but dividing by them gives different infinities
+    fn is_same_constant(left: &Value, right: &Value) -> bool {
+        return match (left, right) {
+            (Value::DOUBLE(left), Value::DOUBLE(right)) => left.to_bits() == right.to_bits(),
+            (left, right) => left == right,
+        };
+    }
+
+    //Points a previously emitted jump at the given instruction
+    pub fn patch_jump(&mut self, jump: usize, target: usize) {
+        match &mut self.code[jump] {
+            Instruction::JUMP(destination) | Instruction::JUMPIFFALSE(destination) | Instruction::JUMPIFTRUE(destination) => *destination = target,
+            instruction => panic!("Cannot patch {:?}, it is not a jump", instruction),
+        }
+    }
+}
+
+pub struct Function {
+    pub name: String,
+    pub arity: usize,
+    pub local_count: usize,
+    pub chunk: Chunk,
+}
+
+pub struct Program {
+    //Top level statements
+    pub main: Function,
+    pub functions: Vec<Function>,
+    pub global_count: usize,
+}
@@ -0,0 +1,335 @@
+use crate::bytecode::{Function, Instruction, Program};
+use crate::syntax_tree::evaluator::{evaluate_binary_operation, evaluate_negation, OutputSinkCell, RuntimeError, StandardOutput, MAX_CALL_DEPTH};
+use crate::syntax_tree::lexer::SourceCodeSpan;
+use crate::syntax_tree::value::Value;
+
+struct CallFrame<'a> {
+    function: &'a Function,
+    ip: usize,
+    locals: Vec<Option<Value>>,
+}
+
+impl<'a> CallFrame<'a> {
+    fn new(function: &'a Function) -> Self {
+        CallFrame { function, ip: 0, locals: vec![None; function.local_count] }
+    }
+}
+
+pub struct VirtualMachine {
+    stack: Vec<Option<Value>>,
+    pub globals: Vec<Option<Value>>,
+    //Mirrors ASTEvaluator::last_value, updated whenever a statement consumes the value of an expression
+    pub last_value: Option<Value>,
+    output: OutputSinkCell,
+}
+
+impl VirtualMachine {
+    pub fn new() -> Self {
+        Self { stack: Vec::new(), globals: Vec::new(), last_value: None, output: StandardOutput::cell() }
+    }
+
+    pub fn set_output(&mut self, output: OutputSinkCell) {
+        self.output = output;
+    }
+
+    //Runs the whole program and returns the value of the last evaluated expression, if any
+    pub fn run(&mut self, program: &Program) -> Result<Option<Value>, RuntimeError> {
+        self.stack.clear();
+        self.last_value = None;
+        self.globals.resize(program.global_count, None);
+        let mut frames = vec![CallFrame::new(&program.main)];
+
+        while let Some(frame) = frames.last_mut() {
+            let function = frame.function;
+            let Some(instruction) = function.chunk.code.get(frame.ip) else {
+                //Only the top level code runs off its end, functions always return
+                frames.pop();
+                continue;
+            };
+            let span = &function.chunk.spans[frame.ip];
+            frame.ip += 1;
+
+            match instruction {
+                Instruction::CONSTANT(index) => self.stack.push(Some(function.chunk.constants[*index].clone())),
+                Instruction::POP => self.last_value = self.pop(),
+                Instruction::LOADLOCAL(slot) => {
+                    let value = frame.locals[*slot].clone();
+                    self.push_variable(value, span)?;
+                }
+                Instruction::STORELOCAL(slot) => {
+                    let value = self.pop_value(span)?;
+                    frame.locals[*slot] = Some(value.clone());
+                    self.last_value = Some(value);
+                }
+                Instruction::LOADGLOBAL(slot) => {
+                    let value = self.globals[*slot].clone();
+                    self.push_variable(value, span)?;
+                }
+                Instruction::STOREGLOBAL(slot) => {
+                    let value = self.pop_value(span)?;
+                    self.globals[*slot] = Some(value.clone());
+                    self.last_value = Some(value);
+                }
+                Instruction::NEGATE => {
+                    let operand = self.pop_value(span)?;
+                    let negated = evaluate_negation(operand).map_err(|message| Self::error(message, span))?;
+                    self.stack.push(Some(negated));
+                }
+                Instruction::NOT => {
+                    let operand = self.pop_integer(span)?;
+                    self.stack.push(Some(Value::INTEGER(!operand)));
+                }
+                Instruction::LOGICALNOT => {
+                    let operand = self.pop_boolean(span)?;
+                    self.stack.push(Some(Value::BOOLEAN(!operand)));
+                }
+                Instruction::BINARY(operator) => {
+                    let (left, right) = self.pop_operands(span)?;
+                    let result = evaluate_binary_operation(operator, left, right).map_err(|message| Self::error(message, span))?;
+                    self.stack.push(Some(result));
+                }
+                Instruction::EQUAL | Instruction::NOTEQUAL => {
+                    let (left, right) = self.pop_operands(span)?;
+                    if left.type_name() != right.type_name() {
+                        return Err(Self::error(format!("Cannot compare values of types {} and {}", left.type_name(), right.type_name()), span));
+                    }
+                    let equal = left == right;
+                    self.stack.push(Some(Value::BOOLEAN(if *instruction == Instruction::EQUAL { equal } else { !equal })));
+                }
+                Instruction::JUMP(target) => frame.ip = *target,
+                Instruction::JUMPIFFALSE(target) | Instruction::JUMPIFTRUE(target) => {
+                    let condition = self.pop_boolean(span)?;
+                    if condition == matches!(instruction, Instruction::JUMPIFTRUE(_)) {
+                        frame.ip = *target;
+                    }
+                }
+                Instruction::CALL(index, argument_count) => {
+                    let arguments = self.pop_arguments(*argument_count, span)?;
+                    if frames.len() > MAX_CALL_DEPTH {
+                        return Err(Self::error(format!("Maximum call depth of {} exceeded", MAX_CALL_DEPTH), span));
+                    }
+                    let mut callee = CallFrame::new(&program.functions[*index]);
+                    for (slot, argument) in arguments.into_iter().enumerate() {
+                        callee.locals[slot] = Some(argument);
+                    }
+                    frames.push(callee);
+                }
+                Instruction::CALLBUILTIN(builtin, argument_count) => {
+                    let arguments = self.pop_arguments(*argument_count, span)?;
+                    builtin.call(&arguments, &self.output);
+                    self.stack.push(None);
+                }
+                Instruction::TOSTRING => {
+                    let value = self.pop_value(span)?;
+                    self.stack.push(Some(Value::STRING(value.to_string())));
+                }
+                Instruction::CONCAT(count) => {
+                    let parts = self.stack.split_off(self.stack.len() - count);
+                    let text = parts.into_iter().flatten().map(|part| part.to_string()).collect();
+                    self.stack.push(Some(Value::STRING(text)));
+                }
+                Instruction::RETURN => {
+                    let value = self.pop_value(span)?;
+                    frames.pop();
+                    self.stack.push(Some(value));
+                }
+                Instruction::RETURNNONE => {
+                    frames.pop();
+                    self.stack.push(None);
+                }
+                Instruction::INVALID => return Err(Self::error("Cannot evaluate an invalid expression".to_string(), span)),
+            }
+        }
+        return Ok(self.last_value.clone());
+    }
+
+    fn error(message: String, span: &Option<SourceCodeSpan>) -> RuntimeError {
+        let span = span.clone().expect("Instructions that can fail carry a source location");
+        return RuntimeError::new(message, span);
+    }
+
+    fn pop(&mut self) -> Option<Value> {
+        return self.stack.pop().expect("Bytecode keeps the stack balanced");
+    }
+
+    fn pop_value(&mut self, span: &Option<SourceCodeSpan>) -> Result<Value, RuntimeError> {
+        return self.pop().ok_or_else(|| Self::error("Expression does not produce a value".to_string(), span));
+    }
+
+    fn pop_arguments(&mut self, count: usize, span: &Option<SourceCodeSpan>) -> Result<Vec<Value>, RuntimeError> {
+        let arguments = self.stack.split_off(self.stack.len() - count);
+        return arguments.into_iter().collect::<Option<Vec<Value>>>()
+            .ok_or_else(|| Self::error("Expression does not produce a value".to_string(), span));
+    }
+
+    fn pop_integer(&mut self, span: &Option<SourceCodeSpan>) -> Result<i64, RuntimeError> {
+        let value = self.pop_value(span)?;
+        return Self::expect_integer(value, span);
+    }
+
+    fn pop_boolean(&mut self, span: &Option<SourceCodeSpan>) -> Result<bool, RuntimeError> {
+        return match self.pop_value(span)? {
+            Value::BOOLEAN(value) => Ok(value),
+            value => Err(Self::error(format!("Expected a value of type Bool, found {}", value.type_name()), span)),
+        };
+    }
+
+    fn expect_integer(value: Value, span: &Option<SourceCodeSpan>) -> Result<i64, RuntimeError> {
+        return match value {
+            Value::INTEGER(value) => Ok(value),
+            value => Err(Self::error(format!("Expected a value of type Int, found {}", value.type_name()), span)),
+        };
+    }
+
+    //The left operand is checked first, like the evaluator does
+    fn pop_operands(&mut self, span: &Option<SourceCodeSpan>) -> Result<(Value, Value), RuntimeError> {
+        let right = self.pop();
+        let left = self.pop_value(span)?;
+        let right = right.ok_or_else(|| Self::error("Expression does not produce a value".to_string(), span))?;
+        return Ok((left, right));
+    }
+
+    fn push_variable(&mut self, value: Option<Value>, span: &Option<SourceCodeSpan>) -> Result<(), RuntimeError> {
+        if value.is_none() {
+            let name = span.as_ref().map(|span| span.literal.as_str()).unwrap_or_default();
+            return Err(Self::error(format!("Undeclared variable '{}'", name), span));
+        }
+        self.stack.push(value);
+        return Ok(());
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use std::cell::RefCell;
+    use std::rc::Rc;
+    use crate::bytecode::compiler::BytecodeCompiler;
+    use crate::bytecode::vm::VirtualMachine;
+    use crate::compilation_unit::CompilationUnit;
+    use crate::syntax_tree::evaluator::ASTEvaluator;
+    use crate::syntax_tree::value::Value;
+
+    fn assert_same_result(input: &str) {
+        let compilation_unit = CompilationUnit::compile(input);
+        assert!(!compilation_unit.diagnostics_vector.borrow().has_errors(), "Expected no errors, but got {:?}", compilation_unit.diagnostics_vector.borrow().diagnostics);
+        let expected = ASTEvaluator::new().evaluate(&compilation_unit.ast);
+        let program = BytecodeCompiler::new().compile(&compilation_unit.ast);
+        let actual = VirtualMachine::new().run(&program);
+        assert_eq!(actual, expected, "Backends disagree on {:?}", input);
+    }
+
+    #[test]
+    fn should_match_evaluator_on_control_flow() {
+        assert_same_result("let a = 2; if a > 1 { 10 } else { 20 }");
+        assert_same_result("if false { 1 } else if 1 == 2 { 2 } else if 1 != 2 { 3 } else { 4 }");
+        assert_same_result("let a = 1; while a == 1 { let a = 0; { if true { break } } let b = 5 } a");
+        assert_same_result("var i = 0; var sum = 0; while true { i += 1; if i == 3 { continue } { if i == 6 { break } } sum += i } sum");
+        assert_same_result("var i = 0; var odd = 0; while i < 10 { i += 1; { let even = i / 2 * 2 == i; if even { continue } } odd += 1 } odd");
+        assert_same_result("let a = 1; { let a = a + 1; { let a = a * 10 } } a");
+        assert_same_result("let a = 1; { let a = a + 1; { let a = a * 10; a } }");
+        assert_same_result("let a = 5");
+        assert_same_result("if true { }");
+        assert_same_result("if 1 > 2 { 5 }");
+        assert_same_result("let a = 4; a; if a > 5 { 5 }");
+        assert_same_result("var i = 0; while i < 3 { i += 1 }");
+        assert_same_result("while false { }");
+        assert_same_result("func f() { }");
+    }
+
+    #[test]
+    fn should_match_evaluator_on_functions() {
+        assert_same_result("let a = add(1, 2); func add(x, y) { return x + y } a * 2");
+        assert_same_result("func fib(n) { if n <= 1 { return n } return fib(n - 1) + fib(n - 2) } fib(15)");
+        assert_same_result("let a = 1; func get() { return a } { let a = 2; get() }");
+        assert_same_result("func f() { while true { { return 7 } } } f()");
+        assert_same_result("func f() { } f()");
+        assert_same_result("let x = get(); let a = 1; func get() { return a }");
+    }
+
+    #[test]
+    fn should_match_evaluator_on_assignments() {
+        assert_same_result("var total = 0; var i = 1; while i <= 10 { total += i; i += 1 } total");
+        assert_same_result("var a = 1; var b = 2; { a = b = 5 } a + b");
+        assert_same_result("var a = 5; a -= 1; a *= 3; a /= 2; a **= 2; a &= 60; a |= 1; a ^= 3");
+        assert_same_result("var count = 0; func bump() { count += 1 } bump(); bump(); count");
+        assert_same_result("func f(n) { var a = n; { a *= 2 } return a } f(21)");
+        assert_same_result("var a = 1; a /= 0");
+    }
+
+    #[test]
+    fn should_match_evaluator_on_strings() {
+        assert_same_result("let name = \"Astra\"; \"Hello, \" + name + \"!\"");
+        assert_same_result("var s = \"a\"; s += \"b\"; s == \"ab\" && s != \"ba\"");
+        assert_same_result("func id(x) { return x } \"a\" + id(1)");
+        assert_same_result("func id(x) { return x } id(\"a\") == 1");
+    }
+
+    #[test]
+    fn should_match_evaluator_on_doubles() {
+        assert_same_result("let r: Double = 1.5; 2.0 * r ** 2.0 - -0.5");
+        assert_same_result("var x = 1e-3; x *= 1e3; x == 1.0");
+        assert_same_result("1.0 / 0.0");
+        assert_same_result("let a = 0.0; let b = -0.0; 1.0 / b");
+        assert_same_result("func id(x) { return x } 1.5 + id(1)");
+        assert_same_result("func id(x) { return x } -id(true)");
+    }
+
+    #[test]
+    fn should_match_evaluator_on_operators() {
+        assert_same_result("1 + 2 * 3 >= 7 == true");
+        assert_same_result("3 < 2 != 2 > 3");
+        assert_same_result("func loop() { return loop() } let a = false && loop(); let b = true || loop(); !a && b");
+        assert_same_result("1 < 2 && 2 < 3 || false");
+        assert_same_result("~5 ^ 3 | 8 & 12");
+        assert_same_result("1 ** 5000000000 + (0 - 1) ** 5000000001");
+        assert_same_result("let a = -7; a / 2 + a % 2 * 10 + a % -2 * 100 + (a >> 1) * 1000 + (a << 3) * 10000");
+        assert_same_result("var a = 3; a %= 2; a <<= 4; a >>= 1; a");
+        assert_same_result("let a = -9223372036854775808; a % -1");
+        assert_same_result("let a = 2; a ** 3 ** 2 - -a ** 2 - 10 - 3");
+    }
+
+    #[test]
+    fn should_match_evaluator_on_runtime_errors() {
+        assert_same_result("func f(n) { return f(n + 1) } f(0)");
+        assert_same_result("func f() { } let a = f()");
+        assert_same_result("func id(x) { return x } 1 + id(true)");
+        assert_same_result("func id(x) { return x } if id(1) { }");
+        assert_same_result("func id(x) { return x } 1 == id(false)");
+        assert_same_result("func id(x) { return x } id(1) && true");
+        assert_same_result("let a = 0; 1 + 10 / a");
+        assert_same_result("2 ** 64");
+        assert_same_result("2 ** (0 - 1)");
+        assert_same_result("let min = (0 - 9223372036854775807) - 1; { -min }");
+        assert_same_result("let a = 64; 1 << a");
+        assert_same_result("let a = -1; 1 >> a");
+        assert_same_result("let a = 0; 1 % a");
+    }
+
+    #[test]
+    fn should_match_evaluator_on_printed_output() {
+        let input = "var i = 0; while i < 3 { print(\"\\(i),\", i ** 2); i += 1 } println(); println(\"a\", \"\\(\"b\") \\(i > 2)\")";
+        let compilation_unit = CompilationUnit::compile(input);
+        let evaluator_output = Rc::new(RefCell::new(String::new()));
+        let mut evaluator = ASTEvaluator::new();
+        evaluator.set_output(evaluator_output.clone());
+        let expected = evaluator.evaluate(&compilation_unit.ast);
+        let vm_output = Rc::new(RefCell::new(String::new()));
+        let mut vm = VirtualMachine::new();
+        vm.set_output(vm_output.clone());
+        assert_eq!(vm.run(&BytecodeCompiler::new().compile(&compilation_unit.ast)), expected);
+        assert_eq!(vm_output.borrow().as_str(), "0, 01, 12, 4\na b true\n");
+        assert_eq!(vm_output, evaluator_output);
+        assert_same_result("func f() { } println(f())");
+        assert_same_result("func f() { } let s = \"\\(f())\"");
+        assert_same_result("var s = \"x\"; \"<\\(s)\\(s + s)>\" == \"<xxx>\"");
+    }
+
+    #[test]
+    fn should_reuse_global_slots_for_redeclarations() {
+        let compilation_unit = CompilationUnit::compile("let a = 1; let a = a + 1; let b = a");
+        let program = BytecodeCompiler::new().compile(&compilation_unit.ast);
+        let mut vm = VirtualMachine::new();
+        assert_eq!(vm.run(&program), Ok(Some(Value::INTEGER(2))));
+        assert_eq!(vm.globals, vec![Some(Value::INTEGER(2)), Some(Value::INTEGER(2))]);
+    }
+}
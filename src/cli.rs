@@ -0,0 +1,249 @@
+use std::io;
+use crate::code::source_code::{SourceCode, STDIN_PATH};
+use crate::compilation_unit::{Backend, CompilationUnit};
+use crate::diagnostics::output::DiagnosticsPrinter;
+use crate::lsp::LanguageServer;
+use crate::repl::Repl;
+use crate::syntax_tree::formatter::ASTFormatter;
+use crate::syntax_tree::lexer::{Lexer, TokenType};
+
+pub const EXIT_SUCCESS: i32 = 0;
+//The program has compile errors
+pub const EXIT_COMPILE_ERROR: i32 = 1;
+pub const EXIT_RUNTIME_ERROR: i32 = 2;
+//Invalid arguments or an unreadable source file
+pub const EXIT_USAGE_ERROR: i32 = 3;
+
+const USAGE: &str = "\
+Usage: astra <command> [options]
+
+Commands:
+  run <files> [--vm]    Compile and run the programs, --vm runs them on the bytecode virtual machine
+  check <files>         Only report diagnostics
+  tokens <files>        Print the tokens produced by the lexer
+  ast <files>           Print the syntax trees
+  fmt <files> [--check] Print the programs with canonical formatting, --check only reports the files that are
+                        not formatted and exits with 1 if there are any
+  repl                  Start an interactive session, also started when no command is given
+  lsp                   Start a language server speaking LSP over the standard input and output
+  help                  Print this message
+
+Every file is a separate program and they are processed in the given order, \"-\" reads a program
+from the standard input. Running \"astra <files>\" is the same as \"astra run <files>\"
+
+Exit codes:
+  0    Success
+  1    The program has compile errors
+  2    The program stopped with a runtime error
+  3    Invalid arguments or unreadable source file
+The exit code of several files is the highest of their exit codes";
+
+#[derive(Debug, PartialEq)]
+pub enum Command {
+    RUN { paths: Vec<String>, backend: Backend },
+    CHECK { paths: Vec<String> },
+    TOKENS { paths: Vec<String> },
+    AST { paths: Vec<String> },
+    FMT { paths: Vec<String>, check: bool },
+    REPL,
+    LSP,
+    HELP,
+}
+
+pub fn parse_arguments(args: &[String]) -> Result<Command, String> {
+    let Some(first) = args.first() else { return Ok(Command::REPL); };
+    let (command, rest) = match first.as_str() {
+        "-h" | "--help" | "help" => return Ok(Command::HELP),
+        "--repl" | "repl" => return expect_no_arguments(Command::REPL, &args[1..]),
+        "lsp" => return expect_no_arguments(Command::LSP, &args[1..]),
+        "run" | "check" | "tokens" | "ast" | "fmt" => (first.as_str(), &args[1..]),
+        //A bare file name runs it
+        _ => ("run", args),
+    };
+
+    let mut paths = Vec::new();
+    let mut backend = Backend::EVALUATOR;
+    let mut check = false;
+    for arg in rest {
+        match arg.as_str() {
+            "--vm" if command == "run" => backend = Backend::VM,
+            "--check" if command == "fmt" => check = true,
+            flag if flag.starts_with('-') && flag != STDIN_PATH => return Err(format!("Unknown option '{}' for command '{}'", flag, command)),
+            file => paths.push(file.to_string()),
+        }
+    }
+    if paths.is_empty() {
+        return Err(format!("Command '{}' expects at least one source file", command));
+    }
+
+    return Ok(match command {
+        "run" => Command::RUN { paths, backend },
+        "check" => Command::CHECK { paths },
+        "tokens" => Command::TOKENS { paths },
+        "ast" => Command::AST { paths },
+        _ => Command::FMT { paths, check },
+    });
+}
+
+fn expect_no_arguments(command: Command, rest: &[String]) -> Result<Command, String> {
+    return match rest.first() {
+        Some(arg) => Err(format!("Unexpected argument '{}'", arg)),
+        None => Ok(command),
+    };
+}
+
+pub fn print_usage_error(message: &str) {
+    eprintln!("Astra Compiler:\n{}\n\n{}", message, USAGE);
+}
+
+//Runs the command and returns the exit code of the process
+pub fn execute(command: Command) -> i32 {
+    return match command {
+        Command::HELP => {
+            println!("{}", USAGE);
+            EXIT_SUCCESS
+        }
+        Command::REPL => {
+            Repl::new().run();
+            EXIT_SUCCESS
+        }
+        Command::LSP => serve_language_server(),
+        Command::RUN { paths, backend } => for_each_source(&paths, |source| run(source, backend)),
+        Command::CHECK { paths } => for_each_source(&paths, check),
+        Command::TOKENS { paths } => for_each_source(&paths, print_tokens),
+        Command::AST { paths } => for_each_source(&paths, print_ast),
+        Command::FMT { paths, check } => for_each_source(&paths, |source| format(source, check)),
+    };
+}
+
+//Files that cannot be read are reported without stopping the remaining ones
+fn for_each_source(paths: &[String], mut action: impl FnMut(SourceCode) -> i32) -> i32 {
+    let mut exit_code = EXIT_SUCCESS;
+    for path in paths {
+        let file_exit_code = match SourceCode::from_path(path) {
+            Ok(source) => action(source),
+            Err(diagnostic) => {
+                DiagnosticsPrinter::new(&SourceCode::named(path.clone(), String::new()), &[diagnostic]).print();
+                EXIT_USAGE_ERROR
+            }
+        };
+        exit_code = exit_code.max(file_exit_code);
+    }
+    return exit_code;
+}
+
+fn serve_language_server() -> i32 {
+    let stdin = io::stdin();
+    let stdout = io::stdout();
+    return match LanguageServer::new().serve(&mut stdin.lock(), &mut stdout.lock()) {
+        Ok(exit_code) => exit_code,
+        Err(err) => {
+            eprintln!("Language server stopped: {}", err);
+            EXIT_USAGE_ERROR
+        }
+    };
+}
+
+fn run(source: SourceCode, backend: Backend) -> i32 {
+    let compilation_unit = CompilationUnit::compile_source(source);
+    compilation_unit.print_diagnostics();
+    if compilation_unit.has_errors() {
+        return EXIT_COMPILE_ERROR;
+    }
+    let result = compilation_unit.run(backend);
+    let exit_code = if result.is_ok() { EXIT_SUCCESS } else { EXIT_RUNTIME_ERROR };
+    compilation_unit.print_result(result);
+    return exit_code;
+}
+
+fn check(source: SourceCode) -> i32 {
+    let compilation_unit = CompilationUnit::compile_source(source);
+    compilation_unit.print_diagnostics();
+    return if compilation_unit.has_errors() { EXIT_COMPILE_ERROR } else { EXIT_SUCCESS };
+}
+
+fn print_tokens(source: SourceCode) -> i32 {
+    let mut lexer = Lexer::new(source.text());
+    let mut exit_code = EXIT_SUCCESS;
+    while let Some(token) = lexer.next_token() {
+        if token.kind == TokenType::WHITESPACE {
+            continue;
+        }
+        if matches!(token.kind,
+            TokenType::INVALID | TokenType::UNTERMINATEDCOMMENT | TokenType::UNTERMINATEDSTRING | TokenType::OVERFLOWINGNUMERAL | TokenType::OVERFLOWINGDOUBLE | TokenType::INVALIDNUMERAL
+        ) {
+            exit_code = EXIT_COMPILE_ERROR;
+        }
+        println!("{:>5}..{:<5} {:<20} {:?}", token.span.start, token.span.end, format!("{:?}", token.kind), token.span.literal);
+    }
+    return exit_code;
+}
+
+fn print_ast(source: SourceCode) -> i32 {
+    let compilation_unit = CompilationUnit::parse_source(source);
+    compilation_unit.ast.visualize();
+    compilation_unit.print_diagnostics();
+    return if compilation_unit.has_errors() { EXIT_COMPILE_ERROR } else { EXIT_SUCCESS };
+}
+
+fn format(source: SourceCode, check: bool) -> i32 {
+    let compilation_unit = CompilationUnit::parse_source(source);
+    compilation_unit.print_diagnostics();
+    //Formatting a tree with syntax errors would drop parts of the program
+    if compilation_unit.has_errors() {
+        return EXIT_COMPILE_ERROR;
+    }
+    let formatted = ASTFormatter::format(&compilation_unit.ast);
+    if !check {
+        print!("{}", formatted);
+        return EXIT_SUCCESS;
+    }
+    if formatted == compilation_unit.source().text() {
+        return EXIT_SUCCESS;
+    }
+    println!("{} is not formatted", compilation_unit.source().name().unwrap_or("<input>"));
+    return EXIT_COMPILE_ERROR;
+}
+
+#[cfg(test)]
+mod test {
+    use crate::cli::{parse_arguments, Command};
+    use crate::compilation_unit::Backend;
+
+    fn paths(args: &[&str]) -> Vec<String> {
+        return args.iter().map(|arg| arg.to_string()).collect();
+    }
+
+    fn parse(args: &[&str]) -> Result<Command, String> {
+        return parse_arguments(&paths(args));
+    }
+
+    #[test]
+    fn should_parse_subcommands() {
+        assert_eq!(parse(&["run", "a.astra", "--vm"]), Ok(Command::RUN { paths: paths(&["a.astra"]), backend: Backend::VM }));
+        assert_eq!(parse(&["--vm", "a.astra"]), Ok(Command::RUN { paths: paths(&["a.astra"]), backend: Backend::VM }));
+        assert_eq!(parse(&["check", "a.astra"]), Ok(Command::CHECK { paths: paths(&["a.astra"]) }));
+        assert_eq!(parse(&["tokens", "a.astra"]), Ok(Command::TOKENS { paths: paths(&["a.astra"]) }));
+        assert_eq!(parse(&["ast", "a.astra"]), Ok(Command::AST { paths: paths(&["a.astra"]) }));
+        assert_eq!(parse(&["fmt", "a.astra"]), Ok(Command::FMT { paths: paths(&["a.astra"]), check: false }));
+        assert_eq!(parse(&["fmt", "--check", "a.astra"]), Ok(Command::FMT { paths: paths(&["a.astra"]), check: true }));
+        assert_eq!(parse(&[]), Ok(Command::REPL));
+        assert_eq!(parse(&["--repl"]), Ok(Command::REPL));
+        assert_eq!(parse(&["lsp"]), Ok(Command::LSP));
+        assert_eq!(parse(&["check", "-h"]), Err("Unknown option '-h' for command 'check'".to_string()));
+    }
+
+    #[test]
+    fn should_reject_invalid_arguments() {
+        assert!(parse(&["check"]).is_err());
+        assert!(parse(&["check", "a.astra", "--vm"]).is_err());
+        assert!(parse(&["run", "a.astra", "--check"]).is_err());
+        assert!(parse(&["repl", "a.astra"]).is_err());
+    }
+
+    #[test]
+    fn should_accept_multiple_files_and_stdin() {
+        assert_eq!(parse(&["check", "a.astra", "-", "dir/b.astra"]), Ok(Command::CHECK { paths: paths(&["a.astra", "-", "dir/b.astra"]) }));
+        assert_eq!(parse(&["a.astra", "b.astra"]), Ok(Command::RUN { paths: paths(&["a.astra", "b.astra"]), backend: Backend::EVALUATOR }));
+    }
+}
@@ -1,43 +1,124 @@
-use std::{env, fs, process};
+use std::fs;
+use std::io::{self, Read};
+use std::path::Path;
+use crate::diagnostics::{Diagnostic, DiagnosticKind};
+use crate::syntax_tree::lexer::SourceCodeSpan;
+
+const EXTENSION: &str = "astra";
+//Path that makes from_path read the program from the standard input
+pub const STDIN_PATH: &str = "-";
 
 pub struct SourceCode {
     text: String,
+    //Path of the file the text was read from, printed along with its diagnostics
+    name: Option<String>,
 }
 
 impl SourceCode {
-    pub fn new(text: String) -> Self { Self { text } }
+    pub fn new(text: String) -> Self { Self { text, name: None } }
 
-    pub fn line_index(&self, position: usize) -> usize { return self.text[..position].lines().count() - 1; }
+    pub fn named(name: String, text: String) -> Self { Self { text, name: Some(name) } }
 
-    pub fn get_line(&self, index: usize) -> &str { return self.text.lines().nth(index).unwrap(); }
+    //Failures are reported as diagnostics that do not point into any source code
+    pub fn from_path(path: &str) -> Result<SourceCode, Diagnostic> {
+        if path == STDIN_PATH {
+            let mut text = String::new();
+            return match io::stdin().read_to_string(&mut text) {
+                Ok(_) => Ok(SourceCode::named("<stdin>".to_string(), text)),
+                Err(err) => Err(Self::io_error(format!("Error reading the standard input: {}", err))),
+            };
+        }
+        if Path::new(path).extension().and_then(|extension| extension.to_str()) != Some(EXTENSION) {
+            return Err(Self::io_error(format!("File \"{}\" has an invalid extension, expected an AstraScript file with the \".{}\" extension", path, EXTENSION)));
+        }
+        return match fs::read_to_string(path) {
+            Ok(text) => Ok(SourceCode::named(path.to_string(), text)),
+            Err(err) => Err(Self::io_error(format!("Error reading file \"{}\": {}", path, err))),
+        };
+    }
 
-    pub fn line_start(&self, index: usize) -> usize { return self.text.lines().take(index).map(|line| line.len() + 1).sum(); }
-}
+    fn io_error(message: String) -> Diagnostic {
+        return Diagnostic::new(message, SourceCodeSpan::new(0, 0, String::new()), DiagnosticKind::ERROR);
+    }
 
-pub fn read_sourcefile() -> String {
-    let args: Vec<String> = env::args().collect();
-    if args.len() < 2 {
-        eprintln!("Astra Compiler:\nNot enough arguments, for help use \"cargo run -- -h\" or \"cargo run -- --help\"");
-        process::exit(1);
+    pub fn text(&self) -> &str { return &self.text; }
+
+    pub fn name(&self) -> Option<&str> { return self.name.as_deref(); }
+
+    pub fn line_index(&self, position: usize) -> usize { return self.text[..position].matches('\n').count(); }
+
+    //Lines may end with "\r\n", the "\r" is not part of the line
+    pub fn get_line(&self, index: usize) -> &str {
+        let line = self.text.split('\n').nth(index).unwrap_or("");
+        return line.strip_suffix('\r').unwrap_or(line);
     }
 
-    if args.contains(&"--help".to_string()) || args.contains(&"-h".to_string()) {
-        eprintln!("Astra Compiler:\nUsage: cargo run -- <filename>");
-        process::exit(0);
+    //Byte offset of the start of the line containing the position
+    pub fn line_start(&self, position: usize) -> usize { return self.text[..position].rfind('\n').map(|newline| newline + 1).unwrap_or(0); }
+}
+
+#[cfg(test)]
+mod test {
+    use std::fs;
+    use std::path::PathBuf;
+    use crate::code::source_code::SourceCode;
+
+    //Removed with everything in it when dropped, every test uses its own since they run in parallel
+    struct TemporaryDirectory {
+        path: PathBuf,
     }
 
-    let passed_arg = &args[1];
+    impl TemporaryDirectory {
+        fn new(test_name: &str) -> Self {
+            let path = std::env::temp_dir().join(format!("astra-source-code-{}-{}", std::process::id(), test_name));
+            return Self { path };
+        }
+
+        fn file(&self, relative_path: &str, text: &str) -> PathBuf {
+            let path = self.path.join(relative_path);
+            fs::create_dir_all(path.parent().unwrap()).unwrap();
+            fs::write(&path, text).unwrap();
+            return path;
+        }
+    }
 
-    if passed_arg.split('.').nth(1) != Some("astra") {
-        eprintln!("Astra Compiler:\nProvided file has invalid extension, expected AstraScript file with \".as\" extension");
-        process::exit(1);
+    impl Drop for TemporaryDirectory {
+        fn drop(&mut self) {
+            let _ = fs::remove_dir_all(&self.path);
+        }
     }
 
-    match fs::read_to_string(passed_arg) {
-        Ok(content) => content,
-        Err(err) => {
-            eprintln!("Astra Compiler:\nError reading file \"{}\": {}", passed_arg, err);
-            process::exit(1);
+    #[test]
+    fn should_read_files_by_their_last_extension() {
+        let directory = TemporaryDirectory::new("extensions");
+        for relative_path in ["prog.astra", "dir/prog.astra", "my.prog.astra", "v1.2/prog.astra"] {
+            let path = directory.file(relative_path, "let a = 1");
+            let source = SourceCode::from_path(path.to_str().unwrap()).expect("Expected the file to be read");
+            assert_eq!(source.text(), "let a = 1");
+            assert_eq!(source.name(), path.to_str());
         }
     }
-}
\ No newline at end of file
+
+    #[test]
+    fn should_find_lines_ending_with_carriage_returns() {
+        let source = SourceCode::new("let a = 1\r\nlet b = 2\r\nx\r\n".to_string());
+        let position = source.text().find('x').unwrap();
+        assert_eq!(source.line_index(position), 2);
+        assert_eq!(source.line_start(position), position);
+        assert_eq!(source.get_line(1), "let b = 2");
+        assert_eq!(source.get_line(2), "x");
+    }
+
+    #[test]
+    fn should_report_invalid_files_as_diagnostics() {
+        let directory = TemporaryDirectory::new("invalid");
+        let path = directory.file("prog.astra.txt", "let a = 1");
+        let error = SourceCode::from_path(path.to_str().unwrap()).err().expect("Expected an invalid extension");
+        assert!(error.message.contains("invalid extension"), "{}", error.message);
+        assert!(error.message.contains("\".astra\""), "{}", error.message);
+
+        let missing = path.with_file_name("missing.astra");
+        let error = SourceCode::from_path(missing.to_str().unwrap()).err().expect("Expected a missing file");
+        assert!(error.message.starts_with("Error reading file"), "{}", error.message);
+    }
+}
@@ -1,37 +1,180 @@
 use std::cell::RefCell;
 use std::collections::HashMap;
 use std::rc::Rc;
-use crate::syntax_tree::{AbstractSyntaxTree, ASTLetStatement, NumberExpression, UnaryExpression, VariableExpression, ASTTraverser, lexer::{Lexer, SourceCodeSpan},evaluator::ASTEvaluator, parser::Parser};
-use crate::diagnostics::{DiagnosticsVectorCell, DiagnosticsVector, output::DiagnosticsPrinter};
+use crate::syntax_tree::{AbstractSyntaxTree, AssignmentExpression, ASTLetStatement, ASTBlockStatement, ASTWhileStatement, ASTFunctionDeclaration, ASTReturnStatement, CallExpression, NumberExpression, UnaryExpression, VariableExpression, ASTTraverser, lexer::{Lexer, SourceCodeSpan, Token},evaluator::{ASTEvaluator, BuiltinFunction, RuntimeError}, value::Value, parser::Parser, scopes::ScopeStack, type_checker::{Type, TypeChecker}, constant_folder::ConstantFolder};
+use crate::diagnostics::{Diagnostic, DiagnosticsVectorCell, DiagnosticsVector, output::DiagnosticsPrinter};
 use crate::code::source_code::SourceCode;
+use crate::bytecode::{compiler::BytecodeCompiler, vm::VirtualMachine};
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum SymbolKind {
+    VARIABLE,
+    //Variable declared with "var"
+    MUTABLEVARIABLE,
+    PARAMETER,
+}
+
+impl SymbolKind {
+    pub fn is_mutable(&self) -> bool { return *self == SymbolKind::MUTABLEVARIABLE; }
+}
+
+//A variable or parameter declared in the source code, hosts declare theirs without a place in it
+#[derive(Debug, Clone, PartialEq)]
+pub struct SymbolDefinition {
+    pub identifier: SourceCodeSpan,
+    pub kind: SymbolKind,
+    //Missing when the program did not get through type checking
+    pub symbol_type: Option<Type>,
+}
+
+//Use of a variable and the identifier of the declaration it resolves to
+#[derive(Debug, Clone, PartialEq)]
+pub struct SymbolReference {
+    pub identifier: SourceCodeSpan,
+    pub definition: SourceCodeSpan,
+}
+
+#[derive(Clone)]
+struct Symbol {
+    //Identifier of the declaration, None for the variables declared by the host
+    definition: Option<SourceCodeSpan>,
+    is_mutable: bool,
+}
+
+#[derive(Clone)]
 struct SymbolChecker {
-    symbols: HashMap<String, ()>,
+    symbols: ScopeStack<Symbol>,
+    definitions: Vec<SymbolDefinition>,
+    references: Vec<SymbolReference>,
+    //Maps every declared function to its number of parameters
+    functions: HashMap<String, usize>,
+    loop_depth: usize,
+    function_depth: usize,
     diagnostics: DiagnosticsVectorCell,
 }
 
 impl SymbolChecker {
     fn new(diagnostics: DiagnosticsVectorCell) -> Self {
         SymbolChecker {
-            symbols: HashMap::new(),
+            symbols: ScopeStack::new(),
+            definitions: Vec::new(),
+            references: Vec::new(),
+            functions: HashMap::new(),
+            loop_depth: 0,
+            function_depth: 0,
             diagnostics,
         }
     }
+
+    //Functions are hoisted so they can be called before their declaration and recursively
+    fn declare_functions(&mut self, ast: &AbstractSyntaxTree) {
+        for function in ast.functions() {
+            if self.functions.contains_key(function.identifier()) || BuiltinFunction::from_name(function.identifier()).is_some() {
+                self.diagnostics.borrow_mut().report_function_redeclaration(&function.identifier);
+                continue;
+            }
+            self.functions.insert(function.identifier().to_string(), function.parameters.len());
+        }
+    }
+
+    fn define(&mut self, identifier: &Token, kind: SymbolKind) {
+        self.definitions.push(SymbolDefinition { identifier: identifier.span.clone(), kind, symbol_type: None });
+        self.symbols.declare(identifier.span.literal.clone(), Symbol { definition: Some(identifier.span.clone()), is_mutable: kind.is_mutable() });
+    }
+
+    fn resolve(&mut self, identifier: &Token) -> Option<&Symbol> {
+        let Some(symbol) = self.symbols.lookup(&identifier.span.literal) else {
+            self.diagnostics.borrow_mut().report_undeclared_variable(identifier);
+            return None;
+        };
+        if let Some(definition) = &symbol.definition {
+            self.references.push(SymbolReference { identifier: identifier.span.clone(), definition: definition.clone() });
+        }
+        return Some(symbol);
+    }
 }
 
 impl ASTTraverser for SymbolChecker {
     fn goto_let_statement(&mut self, let_statement: &ASTLetStatement) {
-        let identifier = let_statement.identifier.span.literal.clone();
         self.goto_expression(&let_statement.initializer);
-        self.symbols.insert(identifier, ());
+        let kind = if let_statement.is_mutable() { SymbolKind::MUTABLEVARIABLE } else { SymbolKind::VARIABLE };
+        self.define(&let_statement.identifier, kind);
+    }
+
+    fn goto_block_statement(&mut self, block_statement: &ASTBlockStatement) {
+        self.symbols.enter_scope();
+        for statement in &block_statement.statements {
+            self.goto_statement(statement);
+        }
+        self.symbols.exit_scope();
+    }
+
+    fn goto_while_statement(&mut self, while_statement: &ASTWhileStatement) {
+        self.goto_expression(&while_statement.condition);
+        self.loop_depth += 1;
+        self.goto_statement(&while_statement.body);
+        self.loop_depth -= 1;
+    }
+
+    fn goto_break_statement(&mut self, break_keyword: &Token) {
+        if self.loop_depth == 0 {
+            self.diagnostics.borrow_mut().report_outside_of_loop(break_keyword);
+        }
+    }
+
+    fn goto_continue_statement(&mut self, continue_keyword: &Token) {
+        if self.loop_depth == 0 {
+            self.diagnostics.borrow_mut().report_outside_of_loop(continue_keyword);
+        }
+    }
+
+    fn goto_function_declaration(&mut self, function_declaration: &ASTFunctionDeclaration) {
+        if !self.symbols.is_global() {
+            self.diagnostics.borrow_mut().report_nested_function(&function_declaration.identifier);
+        }
+        self.symbols.enter_scope();
+        for parameter in &function_declaration.parameters {
+            self.define(&parameter.identifier, SymbolKind::PARAMETER);
+        }
+        let enclosing_loop_depth = self.loop_depth;
+        self.loop_depth = 0;
+        self.function_depth += 1;
+        self.goto_statement(&function_declaration.body);
+        self.function_depth -= 1;
+        self.loop_depth = enclosing_loop_depth;
+        self.symbols.exit_scope();
+    }
+
+    fn goto_return_statement(&mut self, return_statement: &ASTReturnStatement) {
+        if self.function_depth == 0 {
+            self.diagnostics.borrow_mut().report_outside_of_function(&return_statement.return_keyword);
+        }
+        if let Some(return_value) = &return_statement.return_value {
+            self.goto_expression(return_value);
+        }
+    }
+
+    fn goto_call_expression(&mut self, call_expression: &CallExpression) {
+        match self.functions.get(call_expression.identifier()) {
+            _ if BuiltinFunction::from_name(call_expression.identifier()).is_some() => {}
+            None => self.diagnostics.borrow_mut().report_undeclared_function(&call_expression.identifier),
+            Some(&parameter_count) if parameter_count != call_expression.arguments.len() => {
+                self.diagnostics.borrow_mut().report_invalid_argument_count(&call_expression.identifier, parameter_count, call_expression.arguments.len());
+            }
+            Some(_) => {}
+        }
+        for argument in &call_expression.arguments {
+            self.goto_expression(argument);
+        }
     }
 
     fn goto_variable_expression(&mut self, variable_expression: &VariableExpression) {
-        if self.symbols.get(&variable_expression.identifier.span.literal).is_none() {
+        self.resolve(&variable_expression.identifier);
+    }
 
-            let mut diagnostics_binding = self.diagnostics.borrow_mut();
-            diagnostics_binding.report_undeclared_variable(
-                &variable_expression.identifier,
-            );
+    fn goto_assignment_expression(&mut self, assignment_expression: &AssignmentExpression) {
+        self.goto_expression(&assignment_expression.value);
+        if self.resolve(&assignment_expression.identifier).is_some_and(|symbol| !symbol.is_mutable) {
+            self.diagnostics.borrow_mut().report_immutable_assignment(&assignment_expression.identifier);
         }
     }
 
@@ -43,75 +186,186 @@ impl ASTTraverser for SymbolChecker {
 }
 
 
+//Backend used to execute a compiled program, both produce the same results
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum Backend {
+    EVALUATOR,
+    VM,
+}
+
+//Name resolution and type checking passes, their declarations outlive a single compilation so the REPL
+//can check every line against the previous ones
+#[derive(Clone)]
+pub struct SemanticChecker {
+    symbol_checker: SymbolChecker,
+    type_checker: TypeChecker,
+}
+
+impl SemanticChecker {
+    pub fn new(diagnostics: DiagnosticsVectorCell) -> Self {
+        SemanticChecker {
+            symbol_checker: SymbolChecker::new(Rc::clone(&diagnostics)),
+            type_checker: TypeChecker::new(diagnostics),
+        }
+    }
+
+    //Globals provided by the host before any script runs
+    pub fn declare_global(&mut self, name: &str, value_type: Type) {
+        self.symbol_checker.symbols.declare(name.to_string(), Symbol { definition: None, is_mutable: false });
+        self.type_checker.declare_variable(name, value_type);
+    }
+
+    pub fn declare_native_function(&mut self, name: &str, parameters: Vec<Type>, return_type: Type) {
+        self.symbol_checker.functions.insert(name.to_string(), parameters.len());
+        self.type_checker.declare_function(name, parameters, return_type);
+    }
+
+    //Declarations found by the compilations so far, with their types once the type checker got to them
+    pub fn definitions(&self) -> Vec<SymbolDefinition> {
+        return self.symbol_checker.definitions.iter().map(|definition| SymbolDefinition {
+            symbol_type: self.type_checker.declared_type(&definition.identifier),
+            ..definition.clone()
+        }).collect();
+    }
+
+    pub fn references(&self) -> &[SymbolReference] {
+        return &self.symbol_checker.references;
+    }
+}
+
 pub struct CompilationUnit {
     pub ast: AbstractSyntaxTree,
     pub diagnostics_vector: DiagnosticsVectorCell,
+    text: SourceCode,
 }
 
 impl CompilationUnit {
 
     pub fn compile(input: &str) -> CompilationUnit {
+        return Self::compile_source(SourceCode::new(input.to_string()));
+    }
+
+    pub fn compile_source(text: SourceCode) -> CompilationUnit {
+        let diagnostics_bag: DiagnosticsVectorCell = Rc::new(RefCell::new(DiagnosticsVector::new()));
+        let ast = Self::build_ast(text.text(), &diagnostics_bag);
+        let mut checker = SemanticChecker::new(Rc::clone(&diagnostics_bag));
+        return Self::analyze(text, ast, &mut checker, diagnostics_bag);
+    }
+
+    //Compiles input against declarations that earlier compilations left in the checker, the diagnostics bag
+    //has to be the one the checker reports to
+    pub fn compile_with(input: &str, checker: &mut SemanticChecker, diagnostics_bag: DiagnosticsVectorCell) -> CompilationUnit {
         let text = SourceCode::new(input.to_string());
+        let ast = Self::build_ast(input, &diagnostics_bag);
+        return Self::analyze(text, ast, checker, diagnostics_bag);
+    }
+
+    //Only checks the syntax, for tools that work on the tree as it was written
+    pub fn parse(input: &str) -> CompilationUnit {
+        return Self::parse_source(SourceCode::new(input.to_string()));
+    }
+
+    pub fn parse_source(text: SourceCode) -> CompilationUnit {
+        let diagnostics_bag: DiagnosticsVectorCell = Rc::new(RefCell::new(DiagnosticsVector::new()));
+        let ast = Self::build_ast(text.text(), &diagnostics_bag);
+        return Self::init_compilation_unit(text, ast, diagnostics_bag);
+    }
+
+    fn build_ast(input: &str, diagnostics_bag: &DiagnosticsVectorCell) -> AbstractSyntaxTree {
         let mut lexer = Lexer::new(input);
         let mut tokens = Vec::new();
         while let Some(token) = lexer.next_token() {
             tokens.push(token);
         }
-        let diagnostics_bag: DiagnosticsVectorCell = Rc::new(RefCell::new(DiagnosticsVector::new()));
         let mut ast: AbstractSyntaxTree = AbstractSyntaxTree::new();
         let mut parser = Parser::new(
             tokens,
-            Rc::clone(&diagnostics_bag)
+            Rc::clone(diagnostics_bag)
         );
         while let Some(stmt) = parser.next_statement() {
             ast.add_statement(stmt);
         }
-        ast.visualize();
-        if Self::check_diagnostics(&text, &diagnostics_bag).is_err() {
-            return Self::init_compilation_unit(ast, diagnostics_bag);
-        }
-        let mut symbol_checker = SymbolChecker::new(Rc::clone(&diagnostics_bag));
-        ast.visit(&mut symbol_checker);
+        ast.trailing_comments = parser.end_of_file_comments();
+        return ast;
+    }
+
+    //The parser recovers from syntax errors and unknown names get the unknown type, so every checking pass still
+    //reports the problems in the rest of the program, only folding needs a program without errors
+    fn analyze(text: SourceCode, mut ast: AbstractSyntaxTree, checker: &mut SemanticChecker, diagnostics_bag: DiagnosticsVectorCell) -> CompilationUnit {
+        checker.symbol_checker.declare_functions(&ast);
+        ast.visit(&mut checker.symbol_checker);
+        checker.type_checker.declare_functions(&ast);
+        ast.visit(&mut checker.type_checker);
         if Self::check_diagnostics(&text, &diagnostics_bag).is_err() {
-            return Self::init_compilation_unit(ast, diagnostics_bag);
+            return Self::init_compilation_unit(text, ast, diagnostics_bag);
         }
-        Self::init_compilation_unit(ast, diagnostics_bag)
+        let mut constant_folder = ConstantFolder::new(Rc::clone(&diagnostics_bag));
+        constant_folder.fold(&mut ast);
+        Self::init_compilation_unit(text, ast, diagnostics_bag)
+    }
 
+    pub fn has_errors(&self) -> bool {
+        return self.diagnostics_vector.borrow().has_errors();
     }
 
+    pub fn run(&self, backend: Backend) -> Result<Option<Value>, RuntimeError> {
+        return match backend {
+            Backend::EVALUATOR => ASTEvaluator::new().evaluate(&self.ast),
+            Backend::VM => {
+                let program = BytecodeCompiler::new().compile(&self.ast);
+                VirtualMachine::new().run(&program)
+            }
+        };
+    }
 
-    pub fn run_if_valid(&self) {
-        if self.diagnostics_vector.borrow().diagnostics.len() > 0 {
-            return;
+    //Evaluates a program without printing anything, compile errors or the runtime error come back as diagnostics
+    pub fn evaluate(&self) -> Result<Option<Value>, Vec<Diagnostic>> {
+        if self.has_errors() {
+            return Err(self.diagnostics_vector.borrow().diagnostics.clone());
         }
-        self.run();
+        return self.run(Backend::EVALUATOR).map_err(|runtime_error| vec![runtime_error.into()]);
+    }
+
+    pub fn source(&self) -> &SourceCode {
+        return &self.text;
     }
 
-    fn run(&self) {
-        let mut eval = ASTEvaluator::new();
-        self.ast.visit(&mut eval);
-        println!("Result: {:?}", eval.last_value);
+    pub fn print_result(&self, result: Result<Option<Value>, RuntimeError>) {
+        match result {
+            Ok(Some(value)) => println!("Result: {}", value),
+            Ok(None) => {}
+            Err(runtime_error) => {
+                let diagnostics = [runtime_error.into()];
+                DiagnosticsPrinter::new(&self.text, &diagnostics).print();
+            }
+        }
     }
 
-    fn init_compilation_unit(ast: AbstractSyntaxTree, diagnostics_bag: DiagnosticsVectorCell) -> CompilationUnit {
+    fn init_compilation_unit(text: SourceCode, ast: AbstractSyntaxTree, diagnostics_bag: DiagnosticsVectorCell) -> CompilationUnit {
         CompilationUnit {
             ast,
             diagnostics_vector: diagnostics_bag,
+            text,
         }
     }
 
+    //Only errors stop the compilation, warnings are kept along with them
     fn check_diagnostics( code: &SourceCode, diagnostics_bag: &DiagnosticsVectorCell) -> Result<(),()> {
-        let diagnostics_binding = diagnostics_bag.borrow();
-        if diagnostics_binding.diagnostics.len() > 0 {
-            let diagnostics_printer = DiagnosticsPrinter::new(
-                &code,
-                &diagnostics_binding.diagnostics
-            );
-            diagnostics_printer.print();
+        if diagnostics_bag.borrow().has_errors() {
             return Err(());
         }
         Ok(())
     }
 
+    //Compilation itself never prints, callers decide whether the diagnostics are shown
+    pub fn print_diagnostics(&self) {
+        let diagnostics_binding = self.diagnostics_vector.borrow();
+        let diagnostics_printer = DiagnosticsPrinter::new(
+            &self.text,
+            &diagnostics_binding.diagnostics
+        );
+        diagnostics_printer.print();
+    }
+
 
 }
\ No newline at end of file
@@ -2,10 +2,11 @@ use std::cell::RefCell;
 use std::rc::Rc;
 
 use crate::syntax_tree::lexer::{SourceCodeSpan, Token, TokenType};
+use crate::syntax_tree::type_checker::Type;
 
 pub mod output;
 
-#[derive(Clone, Copy,Debug)]
+#[derive(Clone, Copy, Debug, PartialEq)]
 pub enum DiagnosticKind {
     ERROR,
     WARNING,
@@ -13,7 +14,7 @@ pub enum DiagnosticKind {
 }
 
 
-#[derive(Clone,Debug)]
+#[derive(Clone, Debug, PartialEq)]
 pub struct Diagnostic {
     pub message: String,
     pub span: SourceCodeSpan,
@@ -22,7 +23,7 @@ pub struct Diagnostic {
 
 impl Diagnostic {
     pub fn new(message: String, span: SourceCodeSpan, kind: DiagnosticKind) -> Self {
-        Diagnostic { message, span, kind: kind }
+        Diagnostic { message, span, kind }
     }
 }
 
@@ -37,6 +38,10 @@ impl DiagnosticsVector {
         DiagnosticsVector { diagnostics: vec![] }
     }
 
+    pub fn has_errors(&self) -> bool {
+        return self.diagnostics.iter().any(|diagnostic| diagnostic.kind == DiagnosticKind::ERROR);
+    }
+
     pub fn report_error(&mut self, message: String, span: SourceCodeSpan) {
         let error = Diagnostic::new(message, span, DiagnosticKind::ERROR);
         self.diagnostics.push(error);
@@ -54,9 +59,85 @@ impl DiagnosticsVector {
         self.report_error(format!("Expected expression, found <{}>", token.kind), token.span.clone());
     }
 
+    //Points at the opening "/*" instead of everything up to the end of the input
+    pub fn report_unterminated_comment(&mut self, token: &Token) {
+        let opening = SourceCodeSpan::new(token.span.start, token.span.start + 2, "/*".to_string());
+        self.report_error("Unterminated block comment".to_string(), opening);
+    }
+
+    //Points at the opening quote, the literal itself ends with the line
+    pub fn report_unterminated_string(&mut self, token: &Token) {
+        let opening = SourceCodeSpan::new(token.span.start, token.span.start + 1, "\"".to_string());
+        self.report_error("Unterminated string literal".to_string(), opening);
+    }
+
+    pub fn report_invalid_escape(&mut self, span: SourceCodeSpan) {
+        self.report_error(format!("Invalid escape sequence '{}'", span.literal), span);
+    }
+
+    pub fn report_overflowing_numeral(&mut self, token: &Token) {
+        self.report_error(format!("Integer literal '{}' does not fit in 64 bits", token.span.literal), token.span.clone());
+    }
+
+    pub fn report_overflowing_double(&mut self, token: &Token) {
+        self.report_error(format!("Floating-point literal '{}' is too large to be represented", token.span.literal), token.span.clone());
+    }
+
+    pub fn report_invalid_numeral(&mut self, token: &Token) {
+        self.report_error(format!("Invalid numeric literal '{}'", token.span.literal), token.span.clone());
+    }
+
+    pub fn report_missing_separator(&mut self, token: &Token) {
+        self.report_error("Consecutive statements on a line must be separated by ';'".to_string(), token.span.clone());
+    }
+
+    pub fn report_invalid_assignment_target(&mut self, token: &Token) {
+        self.report_error(format!("Only variables can be assigned with <{}>", token.kind), token.span.clone());
+    }
+
     pub fn report_undeclared_variable(&mut self, token: &Token) {
         self.report_error(format!("Undeclared variable '{}'", token.span.literal), token.span.clone());
     }
+
+    pub fn report_immutable_assignment(&mut self, token: &Token) {
+        self.report_error(format!("Cannot assign to immutable variable '{}'", token.span.literal), token.span.clone());
+    }
+
+    pub fn report_outside_of_loop(&mut self, token: &Token) {
+        self.report_error(format!("'{}' used outside of a loop", token.span.literal), token.span.clone());
+    }
+
+    pub fn report_outside_of_function(&mut self, token: &Token) {
+        self.report_error(format!("'{}' used outside of a function", token.span.literal), token.span.clone());
+    }
+
+    pub fn report_nested_function(&mut self, token: &Token) {
+        self.report_error(format!("Function '{}' must be declared at the top level", token.span.literal), token.span.clone());
+    }
+
+    pub fn report_function_redeclaration(&mut self, token: &Token) {
+        self.report_error(format!("Function '{}' is already declared", token.span.literal), token.span.clone());
+    }
+
+    pub fn report_undeclared_function(&mut self, token: &Token) {
+        self.report_error(format!("Undeclared function '{}'", token.span.literal), token.span.clone());
+    }
+
+    pub fn report_unknown_type(&mut self, token: &Token) {
+        self.report_error(format!("Unknown type '{}'", token.span.literal), token.span.clone());
+    }
+
+    pub fn report_type_mismatch(&mut self, expected: &Type, actual: &Type, span: &SourceCodeSpan) {
+        self.report_error(format!("Type mismatch: expected {}, found {}", expected, actual), span.clone());
+    }
+
+    pub fn report_missing_return(&mut self, token: &Token) {
+        self.report_error(format!("Function '{}' does not return a value on every path", token.span.literal), token.span.clone());
+    }
+
+    pub fn report_invalid_argument_count(&mut self, token: &Token, expected: usize, actual: usize) {
+        self.report_error(format!("Function '{}' expects {} arguments, found {}", token.span.literal, expected, actual), token.span.clone());
+    }
 }
 
 #[cfg(test)]
@@ -109,7 +190,7 @@ mod test {
                         diagnostics.push(diagnostic);
                     }
                     _ => {
-                        current_position += 1;
+                        current_position += c.len_utf8();
                     }
                 };
             }
@@ -165,4 +246,337 @@ mod test {
         verifier.verify();
     }
 
-}
\ No newline at end of file
+    #[test]
+    // Should not leak variables declared inside an if branch
+    fn test4() {
+        let input = "if true { let a = 1 } else { let b = «a» } «a»";
+        let expected = vec![
+            "Undeclared variable 'a'",
+            "Undeclared variable 'a'",
+        ];
+
+        let verifier = DiagnosticsVerifier::new(input, expected);
+        verifier.verify();
+    }
+
+    #[test]
+    // Should report missing closing brace
+    fn test5() {
+        let input = "if true { let a = 1 «»";
+        let expected = vec![
+            "Expected <}>, found <EOF>"
+        ];
+
+        let verifier = DiagnosticsVerifier::new(input, expected);
+        verifier.verify();
+    }
+
+    #[test]
+    // Should report break and continue outside of a loop
+    fn test6() {
+        let input = "«break»; while true { if true { break } continue } «continue»";
+        let expected = vec![
+            "'break' used outside of a loop",
+            "'continue' used outside of a loop",
+        ];
+
+        let verifier = DiagnosticsVerifier::new(input, expected);
+        verifier.verify();
+    }
+
+    #[test]
+    // Should not leak variables declared inside a block
+    fn test7() {
+        let input = "let a = 1; { let b = a; { let c = b } «c» } «b»";
+        let expected = vec![
+            "Undeclared variable 'c'",
+            "Undeclared variable 'b'",
+        ];
+
+        let verifier = DiagnosticsVerifier::new(input, expected);
+        verifier.verify();
+    }
+
+    #[test]
+    // Should validate function declarations and calls
+    fn test8() {
+        let input = "func f(a, b) { return a + «c» } «f»(1); «g»(); «return» 1";
+        let expected = vec![
+            "Undeclared variable 'c'",
+            "Function 'f' expects 2 arguments, found 1",
+            "Undeclared function 'g'",
+            "'return' used outside of a function",
+        ];
+
+        let verifier = DiagnosticsVerifier::new(input, expected);
+        verifier.verify();
+    }
+
+    #[test]
+    // Should report redeclared and nested functions
+    fn test9() {
+        let input = "func f() { } func «f»() { func «g»() { } }";
+        let expected = vec![
+            "Function 'f' is already declared",
+            "Function 'g' must be declared at the top level",
+        ];
+
+        let verifier = DiagnosticsVerifier::new(input, expected);
+        verifier.verify();
+    }
+
+    #[test]
+    // Should report type mismatches in expressions and statements
+    fn test10() {
+        let input = "let «a»: Int = true; let b = 1 «+» true; «if» a { } let c: «Foo» = 1; «!»a";
+        let expected = vec![
+            "Type mismatch: expected Int, found Bool",
+            "Type mismatch: expected Int, found Bool",
+            "Type mismatch: expected Bool, found Int",
+            "Unknown type 'Foo'",
+            "Type mismatch: expected Bool, found Int",
+        ];
+
+        let verifier = DiagnosticsVerifier::new(input, expected);
+        verifier.verify();
+    }
+
+    #[test]
+    // Should check function signatures against arguments and return values
+    fn test11() {
+        let input = "func f(x: Int, y) -> Bool { «return» x } let a: Bool = «f»(true, 1); let b = f(1, true) «&&» 1";
+        let expected = vec![
+            "Type mismatch: expected Bool, found Int",
+            "Type mismatch: expected Int, found Bool",
+            "Type mismatch: expected Bool, found Int",
+        ];
+
+        let verifier = DiagnosticsVerifier::new(input, expected);
+        verifier.verify();
+    }
+
+    #[test]
+    // Should warn about constant expressions that always fail
+    fn test12() {
+        let input = "let a = (4 «/» (2 - 2)) + 1; let b = 2 «**» 64; let c = 2 «**» (1 - 3); if false { 1 + (9223372036854775807 «+» 1) }";
+        let expected = vec![
+            "Operation will fail at runtime: Division by zero",
+            "Operation will fail at runtime: Integer overflow",
+            "Operation will fail at runtime: Negative exponent",
+            "Operation will fail at runtime: Integer overflow",
+        ];
+
+        let verifier = DiagnosticsVerifier::new(input, expected);
+        verifier.verify();
+        assert!(verifier.actual.iter().all(|diagnostic| diagnostic.kind == DiagnosticKind::WARNING));
+    }
+
+    #[test]
+    // Should report unterminated block comments and skip the terminated ones
+    fn test13() {
+        let input = "let a = 1 // line\nlet b = /* /* nested */ */ a «/*» /* */ never closed";
+        let expected = vec![
+            "Unterminated block comment"
+        ];
+
+        let verifier = DiagnosticsVerifier::new(input, expected);
+        verifier.verify();
+    }
+
+    #[test]
+    // Should require a separator between statements on the same line
+    fn test14() {
+        let input = "let a = 1; let b = 2 «let» c = 3\n1 «2»; 3\nif a == 1 { } b; // Blocks end their statement\nfunc f() { return }";
+        let expected = vec![
+            "Consecutive statements on a line must be separated by ';'",
+            "Consecutive statements on a line must be separated by ';'",
+        ];
+
+        let verifier = DiagnosticsVerifier::new(input, expected);
+        verifier.verify();
+    }
+
+    #[test]
+    // Should recover from syntax errors at the next statement and keep checking names
+    fn test15() {
+        let input = "let a «5»\nfunc f(x «y») { return z }\nlet b = a + «c»\nf(b)";
+        let expected = vec![
+            "Expected <=>, found <NUMERAL>",
+            "Expected <,>, found <IDENTIFIER>",
+            "Undeclared variable 'c'",
+        ];
+
+        let verifier = DiagnosticsVerifier::new(input, expected);
+        verifier.verify();
+    }
+
+    #[test]
+    // Should leave tokens that belong to other constructs to them
+    fn test16() {
+        let input = "let a = 1\nif a > 1 «let» b = a\nlet c = (b + «)»\nc + «x»";
+        let expected = vec![
+            "Expected <{>, found <LET>",
+            "Expected expression, found <)>",
+            "Undeclared variable 'x'",
+        ];
+
+        let verifier = DiagnosticsVerifier::new(input, expected);
+        verifier.verify();
+    }
+
+    #[test]
+    // Should only allow assigning to variables declared with var
+    fn test17() {
+        let input = "let a = 1\nvar b = 2\n(b) «=» 1\n«a» = 3\nfunc f(x) { «x» = b }\n«c» = 1\nb «+=» true\nb = b + 1";
+        let expected = vec![
+            "Only variables can be assigned with <=>",
+            "Cannot assign to immutable variable 'a'",
+            "Cannot assign to immutable variable 'x'",
+            "Undeclared variable 'c'",
+            "Type mismatch: expected Int, found Bool",
+        ];
+
+        let verifier = DiagnosticsVerifier::new(input, expected);
+        verifier.verify();
+    }
+
+    #[test]
+    // Should point at invalid escapes and at the opening quote of unterminated strings
+    fn test18() {
+        let input = "let a = \"ok\\n \u{e9} «\\q» «\\u{110000}» «\\u»\"\nlet b = «\"»never closed «\\x»\nlet c: String = a «+» 1";
+        let expected = vec![
+            "Invalid escape sequence '\\q'",
+            "Invalid escape sequence '\\u{110000}'",
+            "Invalid escape sequence '\\u'",
+            "Unterminated string literal",
+            "Invalid escape sequence '\\x'",
+            "Type mismatch: expected String, found Int",
+        ];
+
+        let verifier = DiagnosticsVerifier::new(input, expected);
+        verifier.verify();
+    }
+
+    #[test]
+    // Should report numeric literals that do not fit in 64 bits, except i64::MIN, and never mix integers with doubles
+    fn test19() {
+        let input = "let a = «9223372036854775808» + 1\nlet b = «0x1_0000_0000_0000_0000»\nlet c = «0b102»\n\
+            let f = -9223372036854775808 + -0x8000_0000_0000_0000\nlet g = -«9223372036854775809»\nlet h = -«9223372036854775808» ** 1\nlet i = «1e999»\n\
+            let d = 1.5 «*» 2\nlet e = -9223372036854775807 - 1";
+        let expected = vec![
+            "Integer literal '9223372036854775808' does not fit in 64 bits",
+            "Integer literal '0x1_0000_0000_0000_0000' does not fit in 64 bits",
+            "Invalid numeric literal '0b102'",
+            "Integer literal '9223372036854775809' does not fit in 64 bits",
+            "Integer literal '9223372036854775808' does not fit in 64 bits",
+            "Floating-point literal '1e999' is too large to be represented",
+            "Type mismatch: expected Double, found Int",
+        ];
+
+        let verifier = DiagnosticsVerifier::new(input, expected);
+        verifier.verify();
+    }
+
+    #[test]
+    // Should report errors inside interpolations and redeclared builtins
+    fn test20() {
+        let input = "let a = 1\nlet s = \"\\(a +«)»\\(a «a»)\\(«)»\"\nfunc «println»() { }\nprint(s, a, \"\\(«b»)\")";
+        let expected = vec![
+            "Expected expression, found <)>",
+            "Expected <)>, found <IDENTIFIER>",
+            "Expected expression, found <)>",
+            "Function 'println' is already declared",
+            "Undeclared variable 'b'",
+        ];
+
+        let verifier = DiagnosticsVerifier::new(input, expected);
+        verifier.verify();
+    }
+
+    #[test]
+    // Should warn about constant shifts out of range and constant modulo by zero
+    fn test21() {
+        let input = "let a = 1 «<<» 64; let b = 8 «>>» (0 - 1); let c = 7 «%» 0; let d = (1 << 63) >> (63 - 7 % 2)";
+        let expected = vec![
+            "Operation will fail at runtime: Shift amount 64 is out of range",
+            "Operation will fail at runtime: Shift amount -1 is out of range",
+            "Operation will fail at runtime: Division by zero",
+        ];
+
+        let verifier = DiagnosticsVerifier::new(input, expected);
+        verifier.verify();
+        assert!(verifier.actual.iter().all(|diagnostic| diagnostic.kind == DiagnosticKind::WARNING));
+    }
+
+    #[test]
+    // Should not continue an expression with a "-" or "(" that starts the next line, unless inside parentheses
+    fn test22() {
+        let input = "let a: Bool = true\n-1\nfunc f(n: Int) -> Int { return n }\nlet b = «f»\n(2)\nlet c: Int = f(\n2\n-1\n) + (f\n(3)\n- 1)";
+        let expected = vec![
+            "Undeclared variable 'f'",
+        ];
+
+        let verifier = DiagnosticsVerifier::new(input, expected);
+        verifier.verify();
+    }
+
+    #[test]
+    // Should report an operator with two mismatched operands once
+    fn test23() {
+        let input = "let a = true «+» false; let b = \"a\" «<» \"b\"; let c = 1.5 «*» true";
+        let expected = vec![
+            "Type mismatch: expected Int, found Bool",
+            "Type mismatch: expected Int, found String",
+            "Type mismatch: expected Double, found Bool",
+        ];
+
+        let verifier = DiagnosticsVerifier::new(input, expected);
+        verifier.verify();
+    }
+
+    #[test]
+    // Should report functions that can end without returning their value
+    fn test24() {
+        let input = "func «f»(x: Int) -> Int { if x > 0 { return 1 } }\n\
+            func g(x: Int) -> Int { if x > 0 { return 1 } else { return 2 } }\n\
+            func «h»() -> Bool { while true { if false { break } } }\n\
+            func k() -> Int { while true { while true { break } } }\n\
+            func v(x: Int) { if x > 0 { return } }\n\
+            func u(x) { if x { return 1 } }";
+        let expected = vec![
+            "Function 'f' does not return a value on every path",
+            "Function 'h' does not return a value on every path",
+        ];
+
+        let verifier = DiagnosticsVerifier::new(input, expected);
+        verifier.verify();
+    }
+
+    #[test]
+    // Should leave arithmetic on unannotated parameters to the runtime unless the other operand decides the type
+    fn test25() {
+        let input = "func f(a, b) { let s = a + b; let t: String = s; return t }\n\
+            func g(a) { let y = -a; let z: Double = y; return z }\n\
+            func h(a) { let «x»: Bool = a * 2 }";
+        let expected = vec![
+            "Type mismatch: expected Bool, found Int",
+        ];
+
+        let verifier = DiagnosticsVerifier::new(input, expected);
+        verifier.verify();
+    }
+
+    #[test]
+    // Should not warn about operands that a constant left operand of "&&" or "||" skips
+    fn test26() {
+        let input = "let a = false && (1 / 0 == 1); let b = true || 2 ** 64 > 0; let c = true && (1 «/» 0 == 1)";
+        let expected = vec![
+            "Operation will fail at runtime: Division by zero",
+        ];
+
+        let verifier = DiagnosticsVerifier::new(input, expected);
+        verifier.verify();
+        assert!(verifier.actual.iter().all(|diagnostic| diagnostic.kind == DiagnosticKind::WARNING));
+    }
+
+}
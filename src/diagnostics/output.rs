@@ -1,6 +1,6 @@
 use std::cmp;
 use termion::color;
-use crate::diagnostics::Diagnostic;
+use crate::diagnostics::{Diagnostic, DiagnosticKind};
 use crate::code::source_code::SourceCode;
 
 const PREFIX_LENGTH: usize = 8;
@@ -17,38 +17,54 @@ impl <'a> DiagnosticsPrinter<'a> {
     }
 
     pub fn stringify_diagnostic(&self, diagnostic: &Diagnostic) -> String {
+        let (span_color, message_color) = Self::kind_colors(diagnostic.kind);
+        //Failures that happen before there is any source code, like unreadable files, have nothing to point at
+        if self.code.text().is_empty() {
+            return format!("{}{}{}", message_color, diagnostic.message, color::Fg(color::Reset));
+        }
         let line_index = self.code.line_index(diagnostic.span.start);
         let line = self.code.get_line(line_index);
-        let line_start = self.code.line_start(line_index);
+        let line_start = self.code.line_start(diagnostic.span.start);
 
         let column = diagnostic.span.start - line_start;
 
-        let (prefix, span, suffix) = self.get_text_spans(diagnostic, &line, column);
+        let (prefix, span, suffix) = self.get_text_spans(diagnostic, line, column);
 
         let indent = cmp::min(PREFIX_LENGTH, column);
         let arrow_pointers = Self::format_arrow(diagnostic, indent);
-        let error_msg = Self::format_error_message(diagnostic, indent, column, line_index);
-        return format!("{}{}{}{}{}\n{}\n{}{}{}", prefix, color::Fg(color::Red), span, color::Fg(color::Reset), suffix, arrow_pointers, color::Fg(color::LightRed), error_msg, color::Fg(color::Reset),);
+        let error_msg = Self::format_error_message(diagnostic, self.code.name(), indent, column, line_index);
+        return format!("{}{}{}{}{}\n{}\n{}{}{}", prefix, span_color, span, color::Fg(color::Reset), suffix, arrow_pointers, message_color, error_msg, color::Fg(color::Reset),);
+    }
+
+    fn kind_colors(kind: DiagnosticKind) -> (&'static str, &'static str) {
+        return match kind {
+            DiagnosticKind::ERROR => (color::Red.fg_str(), color::LightRed.fg_str()),
+            DiagnosticKind::WARNING => (color::Yellow.fg_str(), color::LightYellow.fg_str()),
+            DiagnosticKind::INFO => (color::Blue.fg_str(), color::LightBlue.fg_str()),
+        };
     }
 
-    fn format_error_message(diagnostic: &Diagnostic, indent: usize, column: usize, line: usize) -> String {
-        return format!("{:indent$}{} at ({},{})", "", diagnostic.message, line, column, indent = indent);
+    fn format_error_message(diagnostic: &Diagnostic, name: Option<&str>, indent: usize, column: usize, line: usize) -> String {
+        return match name {
+            Some(name) => format!("{:indent$}{} at {} ({},{})", "", diagnostic.message, name, line, column, indent = indent),
+            None => format!("{:indent$}{} at ({},{})", "", diagnostic.message, line, column, indent = indent),
+        };
     }
 
     fn format_arrow(diagnostic: &Diagnostic, indent: usize) -> String {
-        let arrow_pointers = format!("{:indent$}{}", "", std::iter::repeat('^')
-        .take( diagnostic.span.length())
-        .collect::<String>(), indent = indent);
+        let arrow_pointers = format!("{:indent$}{}", "", "^".repeat(diagnostic.span.length()), indent = indent);
     
         return arrow_pointers;
     }
 
     fn get_text_spans(&'a self, diagnostic: &Diagnostic, line: &'a str, column: usize) -> (&'a str, &'a str, &'a str) {
-        let prefix_start = cmp::max(0, column as isize - PREFIX_LENGTH as isize) as usize;
-        let prefix_end = column;
+        //The context around the span is measured in bytes, so it is widened to whole characters
+        let prefix_start = line.floor_char_boundary(cmp::max(0, column as isize - PREFIX_LENGTH as isize) as usize);
+        //A span may start at the "\r" that is left out of the line
+        let prefix_end = cmp::min(column, line.len());
 
         let suffix_start = cmp::min(column + diagnostic.span.length(), line.len());
-        let suffix_end = cmp::min(suffix_start + PREFIX_LENGTH, line.len());
+        let suffix_end = line.ceil_char_boundary(cmp::min(suffix_start + PREFIX_LENGTH, line.len()));
 
         let prefix = &line[prefix_start..prefix_end];
         let span = &line[prefix_end..suffix_start];
@@ -0,0 +1,169 @@
+use std::cell::RefCell;
+use std::collections::HashMap;
+use std::rc::Rc;
+use crate::compilation_unit::{CompilationUnit, SemanticChecker};
+use crate::diagnostics::{Diagnostic, DiagnosticKind, DiagnosticsVector, DiagnosticsVectorCell};
+use crate::syntax_tree::evaluator::{ASTEvaluator, Limits, NativeFunction, OutputSinkCell, StandardOutput};
+use crate::syntax_tree::lexer::SourceCodeSpan;
+use crate::syntax_tree::type_checker::Type;
+use crate::syntax_tree::value::{FromValue, Value};
+
+struct NativeFunctionDeclaration {
+    parameters: Vec<Type>,
+    return_type: Type,
+    function: NativeFunction,
+}
+
+//Evaluates scripts on behalf of a Rust host, which provides global variables and native functions and gets
+//back values or diagnostics instead of printed output
+pub struct Engine {
+    variables: HashMap<String, Value>,
+    native_functions: HashMap<String, NativeFunctionDeclaration>,
+    limits: Limits,
+    output: OutputSinkCell,
+}
+
+impl Engine {
+    pub fn new() -> Self {
+        Engine { variables: HashMap::new(), native_functions: HashMap::new(), limits: Limits::default(), output: StandardOutput::cell() }
+    }
+
+    pub fn set_variable(&mut self, name: &str, value: impl Into<Value>) {
+        self.variables.insert(name.to_string(), value.into());
+    }
+
+    //Arguments are checked against the parameter types before the function is called and the returned value against
+    //the return type afterwards, Type::UNKNOWN accepts anything
+    pub fn register_function(&mut self, name: &str, parameters: Vec<Type>, return_type: Type, function: impl Fn(&[Value]) -> Result<Value, String> + 'static) {
+        let function: NativeFunction = Rc::new(function);
+        self.native_functions.insert(name.to_string(), NativeFunctionDeclaration { parameters, return_type, function });
+    }
+
+    pub fn set_limits(&mut self, limits: Limits) {
+        self.limits = limits;
+    }
+
+    //Text printed by scripts goes to the standard output unless the host provides a sink of its own
+    pub fn set_output(&mut self, output: OutputSinkCell) {
+        self.output = output;
+    }
+
+    //Errors are the compile errors of the script or the runtime error that stopped it
+    pub fn evaluate(&self, input: &str) -> Result<Option<Value>, Vec<Diagnostic>> {
+        let diagnostics: DiagnosticsVectorCell = Rc::new(RefCell::new(DiagnosticsVector::new()));
+        let mut checker = SemanticChecker::new(Rc::clone(&diagnostics));
+        for (name, value) in &self.variables {
+            checker.declare_global(name, value.value_type());
+        }
+        for (name, declaration) in &self.native_functions {
+            checker.declare_native_function(name, declaration.parameters.clone(), declaration.return_type);
+        }
+        let compilation_unit = CompilationUnit::compile_with(input, &mut checker, diagnostics);
+        if compilation_unit.has_errors() {
+            return Err(compilation_unit.diagnostics_vector.borrow().diagnostics.clone());
+        }
+
+        let mut evaluator = ASTEvaluator::new();
+        evaluator.set_limits(self.limits);
+        evaluator.set_output(Rc::clone(&self.output));
+        for (name, value) in &self.variables {
+            evaluator.variables.declare(name.clone(), value.clone());
+        }
+        for (name, declaration) in &self.native_functions {
+            evaluator.register_native_function(name, declaration.return_type, Rc::clone(&declaration.function));
+        }
+        return evaluator.evaluate(&compilation_unit.ast).map_err(|runtime_error| vec![runtime_error.into()]);
+    }
+
+    //Evaluates a script that has to produce a value convertible to T
+    pub fn evaluate_as<T: FromValue>(&self, input: &str) -> Result<T, Vec<Diagnostic>> {
+        let end_of_input = SourceCodeSpan::new(input.len(), input.len(), String::new());
+        let Some(value) = self.evaluate(input)? else {
+            return Err(vec![Diagnostic::new("Script does not produce a value".to_string(), end_of_input, DiagnosticKind::ERROR)]);
+        };
+        let type_name = value.type_name();
+        return T::from_value(value).ok_or_else(|| {
+            let message = format!("Expected the script to produce a value of type {}, found {}", T::TYPE, type_name);
+            vec![Diagnostic::new(message, end_of_input, DiagnosticKind::ERROR)]
+        });
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use std::cell::RefCell;
+    use std::rc::Rc;
+    use crate::embedding::Engine;
+    use crate::syntax_tree::evaluator::Limits;
+    use crate::syntax_tree::type_checker::Type;
+    use crate::syntax_tree::value::Value;
+
+    fn messages(result: Result<impl std::fmt::Debug, Vec<crate::diagnostics::Diagnostic>>) -> Vec<String> {
+        return result.expect_err("Expected diagnostics").into_iter().map(|diagnostic| diagnostic.message).collect();
+    }
+
+    #[test]
+    fn should_evaluate_with_host_variables_and_functions() {
+        let mut engine = Engine::new();
+        engine.set_variable("price", 120);
+        engine.set_variable("discounted", true);
+        engine.register_function("max", vec![Type::INT, Type::INT], Type::INT, |arguments| match arguments {
+            [Value::INTEGER(left), Value::INTEGER(right)] => Ok(Value::INTEGER(*left.max(right))),
+            _ => Err("max expects two integers".to_string()),
+        });
+        assert_eq!(engine.evaluate_as::<i64>("if discounted { max(price - 30, 100) } else { price }"), Ok(100));
+        assert_eq!(engine.evaluate_as::<bool>("price > 100"), Ok(true));
+        engine.set_variable("currency", "EUR");
+        engine.set_variable("rate", 0.25);
+        assert_eq!(engine.evaluate_as::<f64>("rate * 2.0"), Ok(0.5));
+        assert_eq!(engine.evaluate_as::<String>("currency + \" \" + \"only\""), Ok("EUR only".to_string()));
+    }
+
+    #[test]
+    fn should_capture_printed_output() {
+        let mut engine = Engine::new();
+        let output = Rc::new(RefCell::new(String::new()));
+        engine.set_output(output.clone());
+        engine.set_variable("user", "Ada");
+        assert_eq!(engine.evaluate("println(\"Hello, \\(user)\"); print(1, 2.5)"), Ok(None));
+        assert_eq!(output.borrow().as_str(), "Hello, Ada\n1 2.5");
+    }
+
+    #[test]
+    fn should_check_scripts_against_host_declarations() {
+        let mut engine = Engine::new();
+        engine.set_variable("price", 120);
+        engine.register_function("half", vec![Type::INT], Type::INT, |arguments| Ok(arguments[0].clone()));
+        assert_eq!(messages(engine.evaluate("half(price, 1) + tax")), vec![
+            "Function 'half' expects 1 arguments, found 2",
+            "Undeclared variable 'tax'",
+        ]);
+        assert_eq!(messages(engine.evaluate("half(true)")), vec!["Type mismatch: expected Int, found Bool"]);
+    }
+
+    #[test]
+    fn should_report_native_failures_and_result_types() {
+        let mut engine = Engine::new();
+        engine.register_function("fail", vec![], Type::INT, |_| Err("Host failure".to_string()));
+        assert_eq!(messages(engine.evaluate("1 + fail()")), vec!["Host failure"]);
+        engine.register_function("lie", vec![], Type::INT, |_| Ok(Value::BOOLEAN(true)));
+        let diagnostics = engine.evaluate("let x: Int = lie(); x + 1").expect_err("Expected diagnostics");
+        assert_eq!(diagnostics[0].message, "Function 'lie' returned a value of type Bool, expected Int");
+        assert_eq!(diagnostics[0].span.literal, "lie");
+        engine.register_function("any", vec![], Type::UNKNOWN, |_| Ok(Value::BOOLEAN(true)));
+        assert_eq!(engine.evaluate_as::<bool>("any()"), Ok(true));
+        engine.register_function("double", vec![Type::INT], Type::INT, |arguments| Ok(arguments[0].clone()));
+        assert_eq!(messages(engine.evaluate("func double(x: Int) -> Int { return x * 2 }")), vec!["Function 'double' is already declared"]);
+        assert_eq!(messages(engine.evaluate_as::<bool>("1 + 2")), vec!["Expected the script to produce a value of type Bool, found Int"]);
+        assert_eq!(messages(engine.evaluate_as::<i64>("func f() { }")), vec!["Script does not produce a value"]);
+    }
+
+    #[test]
+    fn should_enforce_limits() {
+        let mut engine = Engine::new();
+        engine.set_limits(Limits { max_call_depth: 10, max_steps: Some(1000) });
+        assert_eq!(messages(engine.evaluate("while true { }")), vec!["Step limit of 1000 exceeded"]);
+        assert_eq!(messages(engine.evaluate("func f(n) { return f(n + 1) } f(0)")), vec!["Maximum call depth of 10 exceeded"]);
+        assert_eq!(engine.evaluate_as::<i64>("func f(n) { if n == 0 { return 0 } return f(n - 1) } f(9)"), Ok(0));
+    }
+}
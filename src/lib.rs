@@ -0,0 +1,48 @@
+#![allow(dead_code, unused_variables)]
+#![allow(clippy::needless_return, clippy::upper_case_acronyms, clippy::new_without_default)]
+//Compiler and runtime of the Astra language, the astra binary is a thin command line wrapper around this crate
+pub mod syntax_tree;
+pub mod diagnostics;
+pub mod code;
+pub mod compilation_unit;
+pub mod bytecode;
+pub mod repl;
+pub mod cli;
+pub mod embedding;
+pub mod lsp;
+
+pub use compilation_unit::{Backend, CompilationUnit};
+pub use code::source_code::SourceCode;
+pub use diagnostics::{Diagnostic, DiagnosticKind, DiagnosticsVector};
+pub use syntax_tree::{AbstractSyntaxTree, ASTTraverser};
+pub use syntax_tree::lexer::{Lexer, SourceCodeSpan, Token, TokenType};
+pub use syntax_tree::parser::Parser;
+pub use syntax_tree::evaluator::{Limits, OutputSink, OutputSinkCell, RuntimeError, StandardOutput};
+pub use syntax_tree::type_checker::Type;
+pub use syntax_tree::value::FromValue;
+pub use embedding::Engine;
+pub use syntax_tree::value::Value;
+
+//Compiles and evaluates a program, returning the value of its last expression instead of printing it
+pub fn evaluate(input: &str) -> Result<Option<Value>, Vec<Diagnostic>> {
+    return CompilationUnit::compile(input).evaluate();
+}
+
+#[cfg(test)]
+mod test {
+    use crate::{evaluate, Value};
+
+    #[test]
+    fn should_return_values_and_diagnostics() {
+        assert_eq!(evaluate("func square(x: Int) -> Int { return x * x } square(7)"), Ok(Some(Value::INTEGER(49))));
+        assert_eq!(evaluate("let a = 1"), Ok(Some(Value::INTEGER(1))));
+        assert_eq!(evaluate("func f() { }"), Ok(None));
+
+        let errors = evaluate("let a = b + c").expect_err("Expected compile errors");
+        let messages: Vec<&str> = errors.iter().map(|diagnostic| diagnostic.message.as_str()).collect();
+        assert_eq!(messages, vec!["Undeclared variable 'b'", "Undeclared variable 'c'"]);
+
+        let errors = evaluate("let a = 0; 1 / a").expect_err("Expected a runtime error");
+        assert_eq!(errors[0].message, "Division by zero");
+    }
+}
@@ -0,0 +1,373 @@
+use std::cell::RefCell;
+use std::collections::HashMap;
+use std::io::{self, BufRead, Write};
+use std::rc::Rc;
+use serde_json::{json, Value as Json};
+use crate::compilation_unit::{CompilationUnit, SemanticChecker, SymbolDefinition, SymbolKind};
+use crate::diagnostics::{DiagnosticKind, DiagnosticsVector, DiagnosticsVectorCell};
+use crate::syntax_tree::{ASTFunctionDeclaration, ASTLetStatement, ASTTraverser, NumberExpression, UnaryExpression, VariableExpression};
+use crate::syntax_tree::lexer::SourceCodeSpan;
+
+//JSON-RPC error codes
+const PARSE_ERROR: i64 = -32700;
+const METHOD_NOT_FOUND: i64 = -32601;
+//Documents are always sent whole, the programs are small enough to be recompiled on every change
+const FULL_TEXT_SYNC: i64 = 1;
+//LSP symbol kinds
+const FUNCTION_SYMBOL: i64 = 12;
+const VARIABLE_SYMBOL: i64 = 13;
+
+//Language server speaking LSP over a pair of streams, every request recompiles the document it is about
+pub struct LanguageServer {
+    //Text of every open document by its URI
+    documents: HashMap<String, String>,
+    shutdown_requested: bool,
+}
+
+impl LanguageServer {
+    pub fn new() -> Self {
+        LanguageServer { documents: HashMap::new(), shutdown_requested: false }
+    }
+
+    //Serves messages until the client sends exit or closes the input, the returned exit code is 0 only
+    //when the client asked for a shutdown first, as the protocol requires
+    pub fn serve(&mut self, input: &mut impl BufRead, output: &mut impl Write) -> io::Result<i32> {
+        while let Some(body) = read_message(input)? {
+            let replies = match serde_json::from_slice::<Json>(&body) {
+                Ok(message) if message["method"] == "exit" => break,
+                Ok(message) => self.handle_message(&message),
+                Err(err) => vec![error_response(&Json::Null, PARSE_ERROR, format!("Invalid message: {}", err))],
+            };
+            for reply in replies {
+                write_message(output, &reply)?;
+            }
+        }
+        return Ok(if self.shutdown_requested { 0 } else { 1 });
+    }
+
+    //Requests get exactly one response, notifications about documents get their diagnostics published
+    fn handle_message(&mut self, message: &Json) -> Vec<Json> {
+        let method = message["method"].as_str().unwrap_or("");
+        let params = &message["params"];
+        if let Some(id) = message.get("id") {
+            let result = match method {
+                "initialize" => Self::capabilities(),
+                "shutdown" => {
+                    self.shutdown_requested = true;
+                    Json::Null
+                }
+                "textDocument/hover" => self.hover(params),
+                "textDocument/definition" => self.definition(params),
+                "textDocument/documentSymbol" => self.document_symbols(params),
+                _ => return vec![error_response(id, METHOD_NOT_FOUND, format!("Unsupported method '{}'", method))],
+            };
+            return vec![json!({ "jsonrpc": "2.0", "id": id, "result": result })];
+        }
+
+        let uri = params["textDocument"]["uri"].as_str().unwrap_or("").to_string();
+        match method {
+            "textDocument/didOpen" => {
+                let text = params["textDocument"]["text"].as_str().unwrap_or("");
+                self.documents.insert(uri.clone(), text.to_string());
+            }
+            "textDocument/didChange" => {
+                //With full synchronization the last change holds the whole text
+                let Some(text) = params["contentChanges"].as_array().and_then(|changes| changes.last()).and_then(|change| change["text"].as_str()) else { return vec![]; };
+                self.documents.insert(uri.clone(), text.to_string());
+            }
+            //Diagnostics of a closed document are cleared
+            "textDocument/didClose" => {
+                self.documents.remove(&uri);
+                return vec![Self::publish_diagnostics(&uri, vec![])];
+            }
+            _ => return vec![],
+        }
+        let diagnostics = self.diagnostics(&self.documents[&uri]);
+        return vec![Self::publish_diagnostics(&uri, diagnostics)];
+    }
+
+    fn capabilities() -> Json {
+        return json!({
+            "capabilities": {
+                "textDocumentSync": FULL_TEXT_SYNC,
+                "hoverProvider": true,
+                "definitionProvider": true,
+                "documentSymbolProvider": true,
+            },
+            "serverInfo": { "name": "astra" },
+        });
+    }
+
+    fn publish_diagnostics(uri: &str, diagnostics: Vec<Json>) -> Json {
+        return json!({
+            "jsonrpc": "2.0",
+            "method": "textDocument/publishDiagnostics",
+            "params": { "uri": uri, "diagnostics": diagnostics },
+        });
+    }
+
+    fn diagnostics(&self, text: &str) -> Vec<Json> {
+        let (compilation_unit, _) = Self::compile(text);
+        let diagnostics_binding = compilation_unit.diagnostics_vector.borrow();
+        return diagnostics_binding.diagnostics.iter().map(|diagnostic| {
+            let severity = match diagnostic.kind {
+                DiagnosticKind::ERROR => 1,
+                DiagnosticKind::WARNING => 2,
+                DiagnosticKind::INFO => 3,
+            };
+            json!({ "range": range(text, &diagnostic.span), "severity": severity, "source": "astra", "message": diagnostic.message })
+        }).collect();
+    }
+
+    fn compile(text: &str) -> (CompilationUnit, SemanticChecker) {
+        let diagnostics: DiagnosticsVectorCell = Rc::new(RefCell::new(DiagnosticsVector::new()));
+        let mut checker = SemanticChecker::new(Rc::clone(&diagnostics));
+        let compilation_unit = CompilationUnit::compile_with(text, &mut checker, diagnostics);
+        return (compilation_unit, checker);
+    }
+
+    //Text of the document a request is about and the byte offset of its position
+    fn document_position(&self, params: &Json) -> Option<(&str, usize)> {
+        let text = self.documents.get(params["textDocument"]["uri"].as_str()?)?;
+        let line = params["position"]["line"].as_u64()? as usize;
+        let character = params["position"]["character"].as_u64()? as usize;
+        return Some((text, offset(text, line, character)));
+    }
+
+    //Finds the definition of the variable at the offset, whether the offset is on a use or on the declaration itself
+    fn definition_at(text: &str, position: usize) -> Option<SymbolDefinition> {
+        let (_, checker) = Self::compile(text);
+        let contains = |span: &SourceCodeSpan| span.start <= position && position <= span.end;
+        let definitions = checker.definitions();
+        let identifier = match checker.references().iter().find(|reference| contains(&reference.identifier)) {
+            Some(reference) => reference.definition.clone(),
+            None => definitions.iter().find(|definition| contains(&definition.identifier))?.identifier.clone(),
+        };
+        return definitions.into_iter().find(|definition| definition.identifier == identifier);
+    }
+
+    fn definition(&self, params: &Json) -> Json {
+        let Some((text, position)) = self.document_position(params) else { return Json::Null; };
+        let Some(definition) = Self::definition_at(text, position) else { return Json::Null; };
+        return json!({ "uri": params["textDocument"]["uri"], "range": range(text, &definition.identifier) });
+    }
+
+    fn hover(&self, params: &Json) -> Json {
+        let Some((text, position)) = self.document_position(params) else { return Json::Null; };
+        let Some(definition) = Self::definition_at(text, position) else { return Json::Null; };
+        let mut signature = match definition.kind {
+            SymbolKind::VARIABLE => format!("let {}", definition.identifier.literal),
+            SymbolKind::MUTABLEVARIABLE => format!("var {}", definition.identifier.literal),
+            SymbolKind::PARAMETER => format!("(parameter) {}", definition.identifier.literal),
+        };
+        if let Some(symbol_type) = definition.symbol_type {
+            signature.push_str(&format!(": {}", symbol_type));
+        }
+        return json!({
+            "contents": { "kind": "markdown", "value": format!("```astra\n{}\n```", signature) },
+            "range": range(text, &definition.identifier),
+        });
+    }
+
+    fn document_symbols(&self, params: &Json) -> Json {
+        let Some(text) = params["textDocument"]["uri"].as_str().and_then(|uri| self.documents.get(uri)) else { return Json::Null; };
+        let compilation_unit = CompilationUnit::parse(text);
+        let mut collector = SymbolCollector { text, symbols: vec![] };
+        compilation_unit.ast.visit(&mut collector);
+        return Json::Array(collector.symbols);
+    }
+}
+
+//Collects the outline of a document, functions contain their parameters and the variables declared in their body
+struct SymbolCollector<'a> {
+    text: &'a str,
+    symbols: Vec<Json>,
+}
+
+impl SymbolCollector<'_> {
+    fn symbol(&self, span: &SourceCodeSpan, kind: i64, children: Vec<Json>) -> Json {
+        let range = range(self.text, span);
+        return json!({ "name": span.literal, "kind": kind, "range": range, "selectionRange": range, "children": children });
+    }
+}
+
+impl ASTTraverser for SymbolCollector<'_> {
+    fn goto_let_statement(&mut self, let_statement: &ASTLetStatement) {
+        let symbol = self.symbol(&let_statement.identifier.span, VARIABLE_SYMBOL, vec![]);
+        self.symbols.push(symbol);
+    }
+
+    fn goto_function_declaration(&mut self, function_declaration: &ASTFunctionDeclaration) {
+        let enclosing_symbols = std::mem::take(&mut self.symbols);
+        for parameter in &function_declaration.parameters {
+            let symbol = self.symbol(&parameter.identifier.span, VARIABLE_SYMBOL, vec![]);
+            self.symbols.push(symbol);
+        }
+        self.goto_statement(&function_declaration.body);
+        let children = std::mem::replace(&mut self.symbols, enclosing_symbols);
+        let symbol = self.symbol(&function_declaration.identifier.span, FUNCTION_SYMBOL, children);
+        self.symbols.push(symbol);
+    }
+
+    fn goto_variable_expression(&mut self, variable_expression: &VariableExpression) { }
+
+    fn goto_number_expression(&mut self, number: &NumberExpression) { }
+
+    fn goto_error(&mut self, span: &SourceCodeSpan) { }
+
+    fn goto_unary_expression(&mut self, unary_expression: &UnaryExpression) { }
+}
+
+//LSP positions count lines and UTF-16 code units within the line
+fn position(text: &str, offset: usize) -> Json {
+    let offset = offset.min(text.len());
+    let line_start = text[..offset].rfind('\n').map(|newline| newline + 1).unwrap_or(0);
+    let line = text[..offset].matches('\n').count();
+    let character = text[line_start..offset].encode_utf16().count();
+    return json!({ "line": line, "character": character });
+}
+
+fn offset(text: &str, line: usize, character: usize) -> usize {
+    let line_start: usize = text.split_inclusive('\n').take(line).map(str::len).sum();
+    let mut units = 0;
+    for (index, c) in text[line_start.min(text.len())..].char_indices() {
+        if units >= character || c == '\n' {
+            return line_start + index;
+        }
+        units += c.len_utf16();
+    }
+    return text.len();
+}
+
+fn range(text: &str, span: &SourceCodeSpan) -> Json {
+    return json!({ "start": position(text, span.start), "end": position(text, span.end) });
+}
+
+fn error_response(id: &Json, code: i64, message: String) -> Json {
+    return json!({ "jsonrpc": "2.0", "id": id, "error": { "code": code, "message": message } });
+}
+
+//Messages are framed by a Content-Length header, None means the client closed the stream
+fn read_message(input: &mut impl BufRead) -> io::Result<Option<Vec<u8>>> {
+    let mut content_length = None;
+    loop {
+        let mut header = String::new();
+        if input.read_line(&mut header)? == 0 {
+            return Ok(None);
+        }
+        let header = header.trim_end();
+        if header.is_empty() {
+            break;
+        }
+        if let Some(length) = header.strip_prefix("Content-Length:") {
+            content_length = length.trim().parse::<usize>().ok();
+        }
+    }
+    let Some(content_length) = content_length else {
+        return Err(io::Error::new(io::ErrorKind::InvalidData, "Message without a Content-Length header"));
+    };
+    let mut body = vec![0; content_length];
+    input.read_exact(&mut body)?;
+    return Ok(Some(body));
+}
+
+fn write_message(output: &mut impl Write, message: &Json) -> io::Result<()> {
+    let body = message.to_string();
+    write!(output, "Content-Length: {}\r\n\r\n{}", body.len(), body)?;
+    return output.flush();
+}
+
+#[cfg(test)]
+mod test {
+    use serde_json::{json, Value as Json};
+    use crate::lsp::LanguageServer;
+
+    const URI: &str = "file:///test.astra";
+
+    //Plays a client that sends the messages in order and returns everything the server wrote back
+    fn run_session(messages: &[Json]) -> (i32, Vec<Json>) {
+        let mut input = Vec::new();
+        for message in messages {
+            let body = message.to_string();
+            input.extend(format!("Content-Length: {}\r\n\r\n{}", body.len(), body).into_bytes());
+        }
+        let mut output = Vec::new();
+        let exit_code = LanguageServer::new().serve(&mut input.as_slice(), &mut output).unwrap();
+
+        let mut replies = Vec::new();
+        let mut rest = String::from_utf8(output).unwrap();
+        while let Some((header, body)) = rest.split_once("\r\n\r\n") {
+            let length: usize = header.trim_start_matches("Content-Length: ").parse().unwrap();
+            replies.push(serde_json::from_str(&body[..length]).unwrap());
+            rest = body[length..].to_string();
+        }
+        return (exit_code, replies);
+    }
+
+    fn open(text: &str) -> Json {
+        return json!({ "jsonrpc": "2.0", "method": "textDocument/didOpen", "params": { "textDocument": { "uri": URI, "languageId": "astra", "version": 1, "text": text } } });
+    }
+
+    fn request(id: i64, method: &str, line: i64, character: i64) -> Json {
+        return json!({ "jsonrpc": "2.0", "id": id, "method": method, "params": { "textDocument": { "uri": URI }, "position": { "line": line, "character": character } } });
+    }
+
+    fn result(replies: &[Json], id: i64) -> Json {
+        return replies.iter().find(|reply| reply["id"] == id).expect("Expected a response")["result"].clone();
+    }
+
+    #[test]
+    fn should_publish_diagnostics_on_every_change() {
+        let (exit_code, replies) = run_session(&[
+            json!({ "jsonrpc": "2.0", "id": 1, "method": "initialize", "params": { "capabilities": {} } }),
+            json!({ "jsonrpc": "2.0", "method": "initialized", "params": {} }),
+            open("let a = 1\na + b"),
+            json!({ "jsonrpc": "2.0", "method": "textDocument/didChange", "params": { "textDocument": { "uri": URI, "version": 2 }, "contentChanges": [{ "text": "let a = 1\na + 1" }] } }),
+            json!({ "jsonrpc": "2.0", "id": 2, "method": "shutdown" }),
+            json!({ "jsonrpc": "2.0", "method": "exit" }),
+        ]);
+        assert_eq!(exit_code, 0);
+        assert_eq!(result(&replies, 1)["capabilities"]["hoverProvider"], true);
+        let published: Vec<&Json> = replies.iter().filter(|reply| reply["method"] == "textDocument/publishDiagnostics").collect();
+        assert_eq!(published.len(), 2);
+        assert_eq!(published[0]["params"]["diagnostics"], json!([{
+            "range": { "start": { "line": 1, "character": 4 }, "end": { "line": 1, "character": 5 } },
+            "severity": 1,
+            "source": "astra",
+            "message": "Undeclared variable 'b'",
+        }]));
+        assert_eq!(published[1]["params"]["diagnostics"], json!([]));
+    }
+
+    #[test]
+    fn should_resolve_variables_for_definition_and_hover() {
+        let text = "let limit = 10\nfunc clamp(x: Int) -> Int {\n    if x > limit { return limit }\n    return x\n}";
+        let (_, replies) = run_session(&[
+            open(text),
+            request(1, "textDocument/definition", 2, 13),
+            request(2, "textDocument/hover", 3, 11),
+            request(3, "textDocument/hover", 0, 5),
+            request(4, "textDocument/definition", 1, 0),
+        ]);
+        assert_eq!(result(&replies, 1), json!({ "uri": URI, "range": { "start": { "line": 0, "character": 4 }, "end": { "line": 0, "character": 9 } } }));
+        assert_eq!(result(&replies, 2)["contents"]["value"], "```astra\n(parameter) x: Int\n```");
+        assert_eq!(result(&replies, 3)["contents"]["value"], "```astra\nlet limit: Int\n```");
+        assert_eq!(result(&replies, 4), Json::Null);
+    }
+
+    #[test]
+    fn should_list_document_symbols() {
+        let (exit_code, replies) = run_session(&[
+            open("let a = 1\nfunc f(x) { let y = x }"),
+            json!({ "jsonrpc": "2.0", "id": 1, "method": "textDocument/documentSymbol", "params": { "textDocument": { "uri": URI } } }),
+            json!({ "jsonrpc": "2.0", "id": 2, "method": "workspace/symbol", "params": { "query": "" } }),
+        ]);
+        //The client went away without asking for a shutdown
+        assert_eq!(exit_code, 1);
+        let symbols = result(&replies, 1);
+        let names = |symbols: &Json| symbols.as_array().unwrap().iter().map(|symbol| symbol["name"].as_str().unwrap().to_string()).collect::<Vec<_>>();
+        assert_eq!(names(&symbols), vec!["a", "f"]);
+        assert_eq!(names(&symbols[1]["children"]), vec!["x", "y"]);
+        assert_eq!(replies.last().unwrap()["error"]["code"], -32601);
+    }
+}
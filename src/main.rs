@@ -1,12 +1,14 @@
-#![allow(dead_code, unused_variables)]
-use crate::compilation_unit::CompilationUnit;
-mod syntax_tree;
-mod diagnostics;
-mod code;
-mod compilation_unit;
+use std::{env, process};
+use astra::cli;
 
 fn main() {
-    let input = code::source_code::read_sourcefile();
-    let compilation_unit = CompilationUnit::compile(&input);
-    compilation_unit.run_if_valid();
-}
\ No newline at end of file
+    let args: Vec<String> = env::args().skip(1).collect();
+    let exit_code = match cli::parse_arguments(&args) {
+        Ok(command) => cli::execute(command),
+        Err(message) => {
+            cli::print_usage_error(&message);
+            cli::EXIT_USAGE_ERROR
+        }
+    };
+    process::exit(exit_code);
+}
@@ -0,0 +1,153 @@
+use std::cell::RefCell;
+use std::io::{self, BufRead, Write};
+use std::rc::Rc;
+use crate::compilation_unit::{CompilationUnit, SemanticChecker};
+use crate::diagnostics::{DiagnosticsVector, DiagnosticsVectorCell};
+use crate::syntax_tree::evaluator::ASTEvaluator;
+use crate::syntax_tree::lexer::{Lexer, TokenType};
+use crate::syntax_tree::value::Value;
+
+const PROMPT: &str = ">> ";
+const CONTINUATION_PROMPT: &str = ".. ";
+
+//Evaluates input line by line, variables and functions declared by earlier lines stay visible to the later ones
+pub struct Repl {
+    diagnostics: DiagnosticsVectorCell,
+    checker: SemanticChecker,
+    evaluator: ASTEvaluator,
+    //Lines of a statement that is not complete yet
+    buffer: String,
+}
+
+impl Repl {
+    pub fn new() -> Self {
+        let diagnostics: DiagnosticsVectorCell = Rc::new(RefCell::new(DiagnosticsVector::new()));
+        Repl {
+            checker: SemanticChecker::new(Rc::clone(&diagnostics)),
+            diagnostics,
+            evaluator: ASTEvaluator::new(),
+            buffer: String::new(),
+        }
+    }
+
+    pub fn run(&mut self) {
+        println!("Astra REPL, enter :quit or press Ctrl+D to exit");
+        let stdin = io::stdin();
+        let mut lines = stdin.lock().lines();
+        loop {
+            print!("{}", if self.buffer.is_empty() { PROMPT } else { CONTINUATION_PROMPT });
+            io::stdout().flush().unwrap();
+            let Some(Ok(line)) = lines.next() else { break; };
+            if self.buffer.is_empty() && line.trim() == ":quit" {
+                break;
+            }
+            self.feed_line(&line);
+        }
+        println!();
+    }
+
+    //Buffers the line and evaluates the buffered input once it forms complete statements,
+    //an empty line forces the evaluation so the diagnostics tell what is missing
+    fn feed_line(&mut self, line: &str) -> Option<Value> {
+        if !self.buffer.is_empty() {
+            self.buffer.push('\n');
+        }
+        self.buffer.push_str(line);
+        if Self::is_incomplete(&self.buffer) && !line.trim().is_empty() {
+            return None;
+        }
+        let input = std::mem::take(&mut self.buffer);
+        if input.trim().is_empty() {
+            return None;
+        }
+        return self.execute(&input);
+    }
+
+    fn execute(&mut self, input: &str) -> Option<Value> {
+        self.diagnostics.borrow_mut().diagnostics.clear();
+        //Declarations of a line that fails to compile are forgotten
+        let checker_snapshot = self.checker.clone();
+        let compilation_unit = CompilationUnit::compile_with(input, &mut self.checker, Rc::clone(&self.diagnostics));
+        compilation_unit.print_diagnostics();
+        if self.diagnostics.borrow().has_errors() {
+            self.checker = checker_snapshot;
+            return None;
+        }
+        let result = self.evaluator.evaluate(&compilation_unit.ast);
+        let value = result.clone().ok().flatten();
+        compilation_unit.print_result(result);
+        return value;
+    }
+
+    //Input ends in the middle of a statement when a bracket or block comment is left open or the last token expects
+    //something after it
+    fn is_incomplete(input: &str) -> bool {
+        let mut lexer = Lexer::new(input);
+        let mut depth: i64 = 0;
+        let mut last_kind = TokenType::EOF;
+        while let Some(token) = lexer.next_token() {
+            match token.kind {
+                TokenType::LEFTBRACE | TokenType::LEFTPAR => depth += 1,
+                TokenType::RIGHTBRACE | TokenType::RIGHTPAR => depth -= 1,
+                TokenType::UNTERMINATEDCOMMENT => return true,
+                TokenType::EOF => continue,
+                _ if token.kind.is_trivia() => continue,
+                _ => {}
+            }
+            last_kind = token.kind;
+        }
+        let expects_more = matches!(last_kind,
+            TokenType::PLUS | TokenType::MINUS | TokenType::ASTERISK | TokenType::SLASH | TokenType::PERCENT | TokenType::EQUALS
+            | TokenType::AMPERSAND | TokenType::PIPE | TokenType::CARET | TokenType::POWER | TokenType::SHIFTLEFT | TokenType::SHIFTRIGHT
+            | TokenType::NOT | TokenType::BANG
+            | TokenType::EQUALSEQUALS | TokenType::BANGEQUALS | TokenType::LESSTHAN | TokenType::LESSTHANEQUALS
+            | TokenType::GREATERTHAN | TokenType::GREATERTHANEQUALS | TokenType::DOUBLEAMPERSAND | TokenType::DOUBLEPIPE
+            | TokenType::PLUSEQUALS | TokenType::MINUSEQUALS | TokenType::ASTERISKEQUALS | TokenType::SLASHEQUALS | TokenType::PERCENTEQUALS
+            | TokenType::POWEREQUALS | TokenType::SHIFTLEFTEQUALS | TokenType::SHIFTRIGHTEQUALS
+            | TokenType::AMPERSANDEQUALS | TokenType::PIPEEQUALS | TokenType::CARETEQUALS
+            | TokenType::COMMA | TokenType::COLON | TokenType::ARROW | TokenType::ELSE
+        );
+        return depth > 0 || expects_more;
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use crate::repl::Repl;
+    use crate::syntax_tree::value::Value;
+
+    #[test]
+    fn should_keep_declarations_between_lines() {
+        let mut repl = Repl::new();
+        repl.feed_line("let a = 20");
+        repl.feed_line("func double(x: Int) -> Int { return x * 2 }");
+        assert_eq!(repl.feed_line("double(a) + 2"), Some(Value::INTEGER(42)));
+    }
+
+    #[test]
+    fn should_wait_for_incomplete_statements() {
+        let mut repl = Repl::new();
+        assert_eq!(repl.feed_line("func add(x, y) {"), None);
+        assert_eq!(repl.feed_line("    return x +"), None);
+        assert_eq!(repl.feed_line("    y"), None);
+        assert_eq!(repl.feed_line("}"), None);
+        assert_eq!(repl.feed_line("if add(1, 2) == 3 { 1 } else"), None);
+        assert_eq!(repl.feed_line("{ 0 }"), Some(Value::INTEGER(1)));
+        assert_eq!(repl.feed_line("1 + /* still"), None);
+        assert_eq!(repl.feed_line("open */ 2 // done"), Some(Value::INTEGER(3)));
+        assert_eq!(repl.feed_line("var n = 2; n *="), None);
+        assert_eq!(repl.feed_line("3"), Some(Value::INTEGER(6)));
+        assert_eq!(repl.feed_line("n += 1"), Some(Value::INTEGER(7)));
+    }
+
+    #[test]
+    fn should_forget_declarations_of_invalid_lines() {
+        let mut repl = Repl::new();
+        assert_eq!(repl.feed_line("let a = b"), None);
+        assert_eq!(repl.feed_line("let a = 1; let b = a + 1"), Some(Value::INTEGER(2)));
+        //An empty line ends a statement that would otherwise never be completed
+        assert_eq!(repl.feed_line("let c = ("), None);
+        assert_eq!(repl.feed_line(""), None);
+        assert_eq!(repl.feed_line("b"), Some(Value::INTEGER(2)));
+    }
+}
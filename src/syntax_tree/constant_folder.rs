@@ -0,0 +1,183 @@
+use std::rc::Rc;
+use crate::syntax_tree::{AbstractSyntaxTree, ASTStatement, ASTStatementType, BinaryExpression, BinaryOperatorType, Expression, ExpressionType, InterpolationExpression, InterpolationPart, UnaryExpression, UnaryOperatorType};
+use crate::syntax_tree::evaluator::{evaluate_binary_operation, evaluate_negation};
+use crate::syntax_tree::value::Value;
+use crate::syntax_tree::lexer::SourceCodeSpan;
+use crate::diagnostics::DiagnosticsVectorCell;
+
+//Replaces constant subexpressions with their value, operations that would fail at runtime are reported
+//as warnings and kept in the tree since they might never be evaluated, operands a constant left operand of
+//"&&" or "||" skips are not folded at all
+pub struct ConstantFolder {
+    diagnostics: DiagnosticsVectorCell,
+}
+
+impl ConstantFolder {
+    pub fn new(diagnostics: DiagnosticsVectorCell) -> Self {
+        Self { diagnostics }
+    }
+
+    pub fn fold(&mut self, ast: &mut AbstractSyntaxTree) {
+        for statement in &mut ast.statements {
+            self.fold_statement(statement);
+        }
+    }
+
+    fn fold_statement(&mut self, statement: &mut ASTStatement) {
+        match &mut statement.kind {
+            ASTStatementType::EXPRESSION(expr) => self.fold_expression(expr),
+            ASTStatementType::LETSTATEMENT(let_statement) => self.fold_expression(&mut let_statement.initializer),
+            ASTStatementType::BLOCK(block_statement) => {
+                for statement in &mut block_statement.statements {
+                    self.fold_statement(statement);
+                }
+            }
+            ASTStatementType::IF(if_statement) => {
+                self.fold_expression(&mut if_statement.condition);
+                self.fold_statement(&mut if_statement.then_branch);
+                if let Some(else_branch) = &mut if_statement.else_branch {
+                    self.fold_statement(&mut else_branch.else_statement);
+                }
+            }
+            ASTStatementType::WHILE(while_statement) => {
+                self.fold_expression(&mut while_statement.condition);
+                self.fold_statement(&mut while_statement.body);
+            }
+            ASTStatementType::FUNCTION(function) => {
+                //Nothing else holds the declaration before the program runs
+                if let Some(function) = Rc::get_mut(function) {
+                    self.fold_statement(&mut function.body);
+                }
+            }
+            ASTStatementType::RETURN(return_statement) => {
+                if let Some(return_value) = &mut return_statement.return_value {
+                    self.fold_expression(return_value);
+                }
+            }
+            ASTStatementType::BREAK(_) | ASTStatementType::CONTINUE(_) => {}
+        }
+    }
+
+    fn fold_expression(&mut self, expression: &mut Expression) {
+        let folded = match &mut expression.kind {
+            ExpressionType::UNARY(unary_expression) => {
+                self.fold_expression(&mut unary_expression.operand);
+                self.fold_unary_expression(unary_expression)
+            }
+            ExpressionType::BINARY(binary_expression) => {
+                self.fold_expression(&mut binary_expression.left);
+                if Self::is_short_circuited(binary_expression) {
+                    Self::constant_value(&binary_expression.left)
+                } else {
+                    self.fold_expression(&mut binary_expression.right);
+                    self.fold_binary_expression(binary_expression)
+                }
+            }
+            ExpressionType::PARENTHESIZED(parenthesized_expression) => {
+                self.fold_expression(&mut parenthesized_expression.expression);
+                Self::constant_value(&parenthesized_expression.expression)
+            }
+            ExpressionType::CALL(call_expression) => {
+                for argument in &mut call_expression.arguments {
+                    self.fold_expression(argument);
+                }
+                None
+            }
+            ExpressionType::ASSIGNMENT(assignment_expression) => {
+                self.fold_expression(&mut assignment_expression.value);
+                None
+            }
+            ExpressionType::INTERPOLATION(interpolation) => self.fold_interpolation(interpolation),
+            _ => None,
+        };
+        if let Some(value) = folded {
+            *expression = Self::literal(value);
+        }
+    }
+
+    //Constant parts become text, the whole string is a constant once every part is
+    fn fold_interpolation(&mut self, interpolation: &mut InterpolationExpression) -> Option<Value> {
+        for part in &mut interpolation.parts {
+            let InterpolationPart::EXPRESSION(expression, _) = part else { continue; };
+            self.fold_expression(expression);
+            if let Some(value) = Self::constant_value(expression) {
+                *part = InterpolationPart::TEXT(value.to_string());
+            }
+        }
+        let mut text = String::new();
+        for part in &interpolation.parts {
+            let InterpolationPart::TEXT(part_text) = part else { return None; };
+            text.push_str(part_text);
+        }
+        return Some(Value::STRING(text));
+    }
+
+    fn fold_unary_expression(&mut self, unary_expression: &UnaryExpression) -> Option<Value> {
+        let operand = Self::constant_value(&unary_expression.operand)?;
+        return match (&unary_expression.operator.kind, operand) {
+            (UnaryOperatorType::MINUS, operand) => match evaluate_negation(operand) {
+                Ok(negated) => Some(negated),
+                Err(message) => {
+                    self.report_failure(message, &unary_expression.operator.token.span);
+                    None
+                }
+            },
+            (UnaryOperatorType::NOT, Value::INTEGER(operand)) => Some(Value::INTEGER(!operand)),
+            (UnaryOperatorType::LOGICALNOT, Value::BOOLEAN(operand)) => Some(Value::BOOLEAN(!operand)),
+            _ => None,
+        };
+    }
+
+    fn fold_binary_expression(&mut self, binary_expression: &mut BinaryExpression) -> Option<Value> {
+        let left = Self::constant_value(&binary_expression.left);
+        let right = Self::constant_value(&binary_expression.right);
+        return match (&binary_expression.operator.kind, left, right) {
+            (BinaryOperatorType::EQUALS, Some(left), Some(right)) => Some(Value::BOOLEAN(left == right)),
+            (BinaryOperatorType::NOTEQUALS, Some(left), Some(right)) => Some(Value::BOOLEAN(left != right)),
+            (operator, Some(left @ (Value::INTEGER(_) | Value::DOUBLE(_) | Value::STRING(_))), Some(right)) => {
+                match evaluate_binary_operation(operator, left, right) {
+                    Ok(value) => Some(value),
+                    Err(message) => {
+                        self.report_failure(message, &binary_expression.operator.token.span);
+                        None
+                    }
+                }
+            }
+            //A constant left operand that does not skip the right one leaves the result to it
+            (BinaryOperatorType::LOGICALAND | BinaryOperatorType::LOGICALOR, Some(Value::BOOLEAN(_)), right) => right,
+            _ => None,
+        };
+    }
+
+    //"false && x" and "true || x" never evaluate x
+    fn is_short_circuited(binary_expression: &BinaryExpression) -> bool {
+        return matches!(
+            (&binary_expression.operator.kind, Self::constant_value(&binary_expression.left)),
+            (BinaryOperatorType::LOGICALAND, Some(Value::BOOLEAN(false))) | (BinaryOperatorType::LOGICALOR, Some(Value::BOOLEAN(true)))
+        );
+    }
+
+    //The runtime reports the same failure with the same message, the warning says it is only a prediction
+    fn report_failure(&mut self, message: String, span: &SourceCodeSpan) {
+        self.diagnostics.borrow_mut().report_warning(format!("Operation will fail at runtime: {}", message), span.clone());
+    }
+
+    fn constant_value(expression: &Expression) -> Option<Value> {
+        return match &expression.kind {
+            ExpressionType::NUMBER(number) => Some(Value::INTEGER(number.number)),
+            ExpressionType::DOUBLE(double) => Some(Value::DOUBLE(double.value)),
+            ExpressionType::BOOLEAN(boolean) => Some(Value::BOOLEAN(boolean.value)),
+            ExpressionType::STRING(string) => Some(Value::STRING(string.value.clone())),
+            _ => None,
+        };
+    }
+
+    fn literal(value: Value) -> Expression {
+        return match value {
+            Value::INTEGER(number) => Expression::number(number),
+            Value::DOUBLE(value) => Expression::double(value),
+            Value::BOOLEAN(boolean) => Expression::boolean(boolean),
+            Value::STRING(string) => Expression::string(string),
+        };
+    }
+}
@@ -1,63 +1,872 @@
+use std::cell::RefCell;
 use std::collections::HashMap;
-use crate::syntax_tree::{BinaryExpression, BinaryOperatorType, ASTLetStatement, NumberExpression, ParenthesizedExpression, UnaryExpression, UnaryOperatorType, VariableExpression, ASTTraverser};
-use crate::syntax_tree::lexer::SourceCodeSpan;
+use std::io::{self, Write};
+use std::rc::Rc;
+use crate::syntax_tree::{AbstractSyntaxTree, AssignmentExpression, BinaryExpression, BooleanExpression, BinaryOperatorType, ASTLetStatement, ASTBlockStatement, ASTIfStatement, ASTWhileStatement, ASTFunctionDeclaration, ASTReturnStatement, ASTStatement, CallExpression, DoubleExpression, Expression, InterpolationExpression, InterpolationPart, NumberExpression, ParenthesizedExpression, StringExpression, UnaryExpression, UnaryOperatorType, VariableExpression, ASTTraverser};
+use crate::syntax_tree::lexer::{SourceCodeSpan, Token};
+use crate::syntax_tree::scopes::ScopeStack;
+use crate::syntax_tree::type_checker::Type;
+use crate::syntax_tree::value::Value;
+use crate::diagnostics::{Diagnostic, DiagnosticKind};
+
+//Deep enough for ordinary recursion while staying far away from overflowing the Rust stack
+pub const MAX_CALL_DEPTH: usize = 200;
+
+//Host function callable from scripts, an error is reported as a runtime error at the call
+pub type NativeFunction = Rc<dyn Fn(&[Value]) -> Result<Value, String>>;
+
+//Destination of the text printed by scripts, hosts and tests can provide their own to capture it
+pub trait OutputSink {
+    fn write(&mut self, text: &str);
+}
+
+pub type OutputSinkCell = Rc<RefCell<dyn OutputSink>>;
+
+pub struct StandardOutput;
+
+impl StandardOutput {
+    pub fn cell() -> OutputSinkCell {
+        return Rc::new(RefCell::new(StandardOutput));
+    }
+}
+
+impl OutputSink for StandardOutput {
+    //Flushed right away so output without a line break shows up before the next prompt
+    fn write(&mut self, text: &str) {
+        print!("{}", text);
+        io::stdout().flush().unwrap();
+    }
+}
+
+impl OutputSink for String {
+    fn write(&mut self, text: &str) {
+        self.push_str(text);
+    }
+}
+
+//Functions every script can call, they cannot be redeclared and take any number of arguments of any type
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum BuiltinFunction {
+    PRINT,
+    PRINTLN,
+}
+
+impl BuiltinFunction {
+    pub fn from_name(name: &str) -> Option<BuiltinFunction> {
+        return match name {
+            "print" => Some(BuiltinFunction::PRINT),
+            "println" => Some(BuiltinFunction::PRINTLN),
+            _ => None,
+        };
+    }
+
+    //Shared by every backend, the arguments are separated by spaces like in Swift
+    pub fn call(&self, arguments: &[Value], output: &OutputSinkCell) {
+        let mut text = arguments.iter().map(Value::to_string).collect::<Vec<String>>().join(" ");
+        if *self == BuiltinFunction::PRINTLN {
+            text.push('\n');
+        }
+        output.borrow_mut().write(&text);
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct Limits {
+    //Calls are evaluated on the Rust stack, so this can only be lowered below MAX_CALL_DEPTH
+    pub max_call_depth: usize,
+    //Loop iterations and function calls a single evaluation may perform
+    pub max_steps: Option<usize>,
+}
+
+impl Default for Limits {
+    fn default() -> Self {
+        Limits { max_call_depth: MAX_CALL_DEPTH, max_steps: None }
+    }
+}
+
+#[derive(Debug, PartialEq, Clone)]
+pub struct RuntimeError {
+    pub message: String,
+    pub span: SourceCodeSpan,
+}
+
+impl RuntimeError {
+    pub fn new(message: String, span: SourceCodeSpan) -> Self {
+        RuntimeError { message, span }
+    }
+}
+
+impl From<RuntimeError> for Diagnostic {
+    fn from(error: RuntimeError) -> Self {
+        Diagnostic::new(error.message, error.span, DiagnosticKind::ERROR)
+    }
+}
+
+//Checked integer arithmetic shared by every backend, the error is the message to report at the operator.
+//Division truncates toward zero and the remainder takes the sign of the dividend, so -7 / 2 == -3 and -7 % 2 == -1.
+//Any integer divided by -1 leaves no remainder, so i64::MIN % -1 is 0 even though i64::MIN / -1 overflows
+pub fn evaluate_integer_operation(operator: &BinaryOperatorType, left: i64, right: i64) -> Result<Value, String> {
+    let overflow = || "Integer overflow".to_string();
+    return match operator {
+        BinaryOperatorType::PLUS => left.checked_add(right).map(Value::INTEGER).ok_or_else(overflow),
+        BinaryOperatorType::MINUS => left.checked_sub(right).map(Value::INTEGER).ok_or_else(overflow),
+        BinaryOperatorType::MULTIPLY => left.checked_mul(right).map(Value::INTEGER).ok_or_else(overflow),
+        BinaryOperatorType::DIVIDE if right == 0 => Err("Division by zero".to_string()),
+        BinaryOperatorType::DIVIDE => left.checked_div(right).map(Value::INTEGER).ok_or_else(overflow),
+        BinaryOperatorType::MODULO if right == 0 => Err("Division by zero".to_string()),
+        BinaryOperatorType::MODULO => Ok(Value::INTEGER(left.wrapping_rem(right))),
+        BinaryOperatorType::SHIFTLEFT | BinaryOperatorType::SHIFTRIGHT => evaluate_shift(operator, left, right).map(Value::INTEGER),
+        BinaryOperatorType::POWER => evaluate_power(left, right).map(Value::INTEGER),
+        BinaryOperatorType::AND => Ok(Value::INTEGER(left & right)),
+        BinaryOperatorType::OR => Ok(Value::INTEGER(left | right)),
+        BinaryOperatorType::XOR => Ok(Value::INTEGER(left ^ right)),
+        BinaryOperatorType::LESSTHAN => Ok(Value::BOOLEAN(left < right)),
+        BinaryOperatorType::LESSTHANOREQUAL => Ok(Value::BOOLEAN(left <= right)),
+        BinaryOperatorType::GREATERTHAN => Ok(Value::BOOLEAN(left > right)),
+        BinaryOperatorType::GREATERTHANOREQUAL => Ok(Value::BOOLEAN(left >= right)),
+        BinaryOperatorType::EQUALS => Ok(Value::BOOLEAN(left == right)),
+        BinaryOperatorType::NOTEQUALS => Ok(Value::BOOLEAN(left != right)),
+        BinaryOperatorType::LOGICALAND | BinaryOperatorType::LOGICALOR => Err(format!("Operator {:?} cannot be applied to integers", operator)),
+    };
+}
+
+//Operators other than equality and the logical ones, "+" also concatenates strings
+pub fn evaluate_binary_operation(operator: &BinaryOperatorType, left: Value, right: Value) -> Result<Value, String> {
+    return match (operator, left, right) {
+        (BinaryOperatorType::PLUS, Value::STRING(left), Value::STRING(right)) => Ok(Value::STRING(left + &right)),
+        (BinaryOperatorType::PLUS, Value::STRING(_), right) => Err(format!("Expected a value of type String, found {}", right.type_name())),
+        (operator, Value::INTEGER(left), Value::INTEGER(right)) => evaluate_integer_operation(operator, left, right),
+        (operator, Value::DOUBLE(left), Value::DOUBLE(right)) => evaluate_double_operation(operator, left, right),
+        (_, Value::DOUBLE(_), value) => Err(format!("Expected a value of type Double, found {}", value.type_name())),
+        (_, Value::INTEGER(_), value) | (_, value, _) => Err(format!("Expected a value of type Int, found {}", value.type_name())),
+    };
+}
+
+//Doubles follow IEEE 754, so dividing by zero gives an infinity instead of an error
+fn evaluate_double_operation(operator: &BinaryOperatorType, left: f64, right: f64) -> Result<Value, String> {
+    return match operator {
+        BinaryOperatorType::PLUS => Ok(Value::DOUBLE(left + right)),
+        BinaryOperatorType::MINUS => Ok(Value::DOUBLE(left - right)),
+        BinaryOperatorType::MULTIPLY => Ok(Value::DOUBLE(left * right)),
+        BinaryOperatorType::DIVIDE => Ok(Value::DOUBLE(left / right)),
+        BinaryOperatorType::MODULO => Ok(Value::DOUBLE(left % right)),
+        BinaryOperatorType::POWER => Ok(Value::DOUBLE(left.powf(right))),
+        BinaryOperatorType::LESSTHAN => Ok(Value::BOOLEAN(left < right)),
+        BinaryOperatorType::LESSTHANOREQUAL => Ok(Value::BOOLEAN(left <= right)),
+        BinaryOperatorType::GREATERTHAN => Ok(Value::BOOLEAN(left > right)),
+        BinaryOperatorType::GREATERTHANOREQUAL => Ok(Value::BOOLEAN(left >= right)),
+        BinaryOperatorType::EQUALS => Ok(Value::BOOLEAN(left == right)),
+        BinaryOperatorType::NOTEQUALS => Ok(Value::BOOLEAN(left != right)),
+        BinaryOperatorType::AND | BinaryOperatorType::OR | BinaryOperatorType::XOR
+        | BinaryOperatorType::SHIFTLEFT | BinaryOperatorType::SHIFTRIGHT => Err("Expected a value of type Int, found Double".to_string()),
+        BinaryOperatorType::LOGICALAND | BinaryOperatorType::LOGICALOR => Err(format!("Operator {:?} cannot be applied to doubles", operator)),
+    };
+}
+
+pub fn evaluate_negation(operand: Value) -> Result<Value, String> {
+    return match operand {
+        Value::INTEGER(operand) => operand.checked_neg().map(Value::INTEGER).ok_or_else(|| "Integer overflow".to_string()),
+        Value::DOUBLE(operand) => Ok(Value::DOUBLE(-operand)),
+        operand => Err(format!("Expected a value of type Int, found {}", operand.type_name())),
+    };
+}
+
+//Bits shifted out on the left are discarded, shifting right keeps the sign
+fn evaluate_shift(operator: &BinaryOperatorType, value: i64, amount: i64) -> Result<i64, String> {
+    let amount = match u32::try_from(amount) {
+        Ok(amount) if amount < i64::BITS => amount,
+        _ => return Err(format!("Shift amount {} is out of range", amount)),
+    };
+    return match operator {
+        BinaryOperatorType::SHIFTLEFT => Ok(value << amount),
+        _ => Ok(value >> amount),
+    };
+}
+
+fn evaluate_power(base: i64, exponent: i64) -> Result<i64, String> {
+    if exponent < 0 {
+        return Err("Negative exponent".to_string());
+    }
+    return match u32::try_from(exponent) {
+        Ok(exponent) => base.checked_pow(exponent).ok_or_else(|| "Integer overflow".to_string()),
+        //Only these bases stay in range for exponents that do not even fit in 32 bits
+        Err(_) => match base {
+            0 | 1 => Ok(base),
+            -1 => Ok(if exponent % 2 == 0 { 1 } else { -1 }),
+            _ => Err("Integer overflow".to_string()),
+        },
+    };
+}
+
+//Pending jump raised by a statement; it unwinds the enclosing bodies until a loop or a call handles it
+#[derive(Debug, PartialEq)]
+enum ControlFlow {
+    BREAK,
+    CONTINUE,
+    RETURN,
+}
 
 pub struct ASTEvaluator {
-    pub last_value: Option<i64>,
-    pub variables: HashMap<String, i64>,
+    pub last_value: Option<Value>,
+    pub variables: ScopeStack<Value>,
+    runtime_error: Option<RuntimeError>,
+    functions: HashMap<String, Rc<ASTFunctionDeclaration>>,
+    //Declared return type and the function, the host may not keep its promise
+    native_functions: HashMap<String, (Type, NativeFunction)>,
+    control_flow: Option<ControlFlow>,
+    call_depth: usize,
+    limits: Limits,
+    steps: usize,
+    output: OutputSinkCell,
 }
 
 impl ASTEvaluator {
     pub fn new() -> Self {
-        Self { last_value: None, variables: HashMap::new() }
+        Self {
+            last_value: None,
+            variables: ScopeStack::new(),
+            runtime_error: None,
+            functions: HashMap::new(),
+            native_functions: HashMap::new(),
+            control_flow: None,
+            call_depth: 0,
+            limits: Limits::default(),
+            steps: 0,
+            output: StandardOutput::cell(),
+        }
+    }
+
+    pub fn set_output(&mut self, output: OutputSinkCell) {
+        self.output = output;
+    }
+
+    pub fn set_limits(&mut self, limits: Limits) {
+        self.limits = Limits { max_call_depth: limits.max_call_depth.min(MAX_CALL_DEPTH), ..limits };
+    }
+
+    //The checker reports script functions with the same name as redeclarations, returned values are checked
+    //against the return type, Type::UNKNOWN accepts anything
+    pub fn register_native_function(&mut self, name: &str, return_type: Type, function: NativeFunction) {
+        self.native_functions.insert(name.to_string(), (return_type, function));
+    }
+
+    //Runs the whole program and returns the value of the last evaluated expression, if any
+    pub fn evaluate(&mut self, ast: &AbstractSyntaxTree) -> Result<Option<Value>, RuntimeError> {
+        self.declare_functions(ast);
+        self.last_value = None;
+        self.steps = 0;
+        ast.visit(self);
+        if let Some(runtime_error) = self.runtime_error.take() {
+            return Err(runtime_error);
+        }
+        return Ok(self.last_value.clone());
+    }
+
+    pub fn declare_functions(&mut self, ast: &AbstractSyntaxTree) {
+        for function in ast.functions() {
+            self.functions.insert(function.identifier().to_string(), Rc::clone(function));
+        }
+    }
+
+    fn report_runtime_error(&mut self, message: String, span: &SourceCodeSpan) {
+        self.runtime_error = Some(RuntimeError::new(message, span.clone()));
+    }
+
+    //Counts a loop iteration or a call, the step limit is what stops scripts that never terminate
+    fn take_step(&mut self, span: &SourceCodeSpan) -> bool {
+        self.steps += 1;
+        if let Some(max_steps) = self.limits.max_steps && self.steps > max_steps {
+            self.report_runtime_error(format!("Step limit of {} exceeded", max_steps), span);
+            return false;
+        }
+        return true;
+    }
+
+    fn evaluate_arguments(&mut self, call_expression: &CallExpression) -> Option<Vec<Value>> {
+        let mut arguments = Vec::new();
+        for argument in &call_expression.arguments {
+            self.goto_expression(argument);
+            arguments.push(self.expect_value(&call_expression.identifier.span)?);
+        }
+        return Some(arguments);
+    }
+
+    fn call_native_function(&mut self, return_type: Type, function: NativeFunction, call_expression: &CallExpression) {
+        let Some(arguments) = self.evaluate_arguments(call_expression) else { return; };
+        if !self.take_step(&call_expression.identifier.span) {
+            return;
+        }
+        match function(&arguments) {
+            Ok(value) if !value.value_type().is_assignable_to(&return_type) => {
+                let message = format!("Function '{}' returned a value of type {}, expected {}", call_expression.identifier(), value.type_name(), return_type);
+                self.report_runtime_error(message, &call_expression.identifier.span);
+            }
+            Ok(value) => self.last_value = Some(value),
+            Err(message) => self.report_runtime_error(message, &call_expression.identifier.span),
+        }
+    }
+
+    fn call_builtin_function(&mut self, builtin: BuiltinFunction, call_expression: &CallExpression) {
+        let Some(arguments) = self.evaluate_arguments(call_expression) else { return; };
+        if !self.take_step(&call_expression.identifier.span) {
+            return;
+        }
+        builtin.call(&arguments, &self.output);
+        self.last_value = None;
+    }
+
+    //True while a runtime error or a pending jump is skipping the remaining statements
+    fn is_unwinding(&self) -> bool {
+        return self.runtime_error.is_some() || self.control_flow.is_some();
+    }
+
+    //Reads the value produced by the last expression, calls to functions that did not return anything produce none
+    fn expect_value(&mut self, span: &SourceCodeSpan) -> Option<Value> {
+        if self.runtime_error.is_some() {
+            return None;
+        }
+        if self.last_value.is_none() {
+            self.report_runtime_error("Expression does not produce a value".to_string(), span);
+        }
+        return self.last_value.clone();
+    }
+
+    fn expect_integer(&mut self, span: &SourceCodeSpan) -> Option<i64> {
+        return match self.expect_value(span)? {
+            Value::INTEGER(value) => Some(value),
+            value => {
+                self.report_runtime_error(format!("Expected a value of type Int, found {}", value.type_name()), span);
+                None
+            }
+        };
+    }
+
+    fn expect_boolean(&mut self, span: &SourceCodeSpan) -> Option<bool> {
+        return match self.expect_value(span)? {
+            Value::BOOLEAN(value) => Some(value),
+            value => {
+                self.report_runtime_error(format!("Expected a value of type Bool, found {}", value.type_name()), span);
+                None
+            }
+        };
+    }
+
+    //Conditions of if and while statements are not the value of the program, the last value is left as it was
+    fn evaluate_condition(&mut self, condition: &Expression, span: &SourceCodeSpan) -> Option<bool> {
+        let last_value = self.last_value.take();
+        self.goto_expression(condition);
+        let condition = self.expect_boolean(span);
+        self.last_value = last_value;
+        return condition;
+    }
+
+    fn evaluate_logical_expression(&mut self, expr: &BinaryExpression) {
+        let span = &expr.operator.token.span;
+        self.goto_expression(&expr.left);
+        let Some(left) = self.expect_boolean(span) else { return; };
+        //The right operand is skipped whenever the left one already decides the result
+        let short_circuit = match expr.operator.kind {
+            BinaryOperatorType::LOGICALAND => !left,
+            _ => left,
+        };
+        if short_circuit {
+            self.last_value = Some(Value::BOOLEAN(left));
+            return;
+        }
+        self.goto_expression(&expr.right);
+        let Some(right) = self.expect_boolean(span) else { return; };
+        self.last_value = Some(Value::BOOLEAN(right));
+    }
+
+    fn evaluate_equality_expression(&mut self, expr: &BinaryExpression) {
+        let span = &expr.operator.token.span;
+        self.goto_expression(&expr.left);
+        let Some(left) = self.expect_value(span) else { return; };
+        self.goto_expression(&expr.right);
+        let Some(right) = self.expect_value(span) else { return; };
+        if left.type_name() != right.type_name() {
+            self.report_runtime_error(format!("Cannot compare values of types {} and {}", left.type_name(), right.type_name()), span);
+            return;
+        }
+        let equal = left == right;
+        self.last_value = Some(Value::BOOLEAN(match expr.operator.kind {
+            BinaryOperatorType::EQUALS => equal,
+            _ => !equal,
+        }));
     }
 }
 
 impl ASTTraverser for ASTEvaluator {
+    fn goto_statement(&mut self, statement: &ASTStatement) {
+        if self.is_unwinding() {
+            return;
+        }
+        self.do_visit_statement(statement);
+    }
+
     fn goto_let_statement(&mut self, let_statement: &ASTLetStatement) {
         self.goto_expression(&let_statement.initializer);
-        self.variables.insert(let_statement.identifier.span.literal.clone(), self.last_value.unwrap());
+        let Some(value) = self.expect_value(&let_statement.identifier.span) else { return; };
+        self.variables.declare(let_statement.identifier.span.literal.clone(), value);
+    }
+
+    fn goto_block_statement(&mut self, block_statement: &ASTBlockStatement) {
+        self.variables.enter_scope();
+        for statement in &block_statement.statements {
+            self.goto_statement(statement);
+            if self.is_unwinding() {
+                break;
+            }
+        }
+        self.variables.exit_scope();
+    }
+
+    fn goto_if_statement(&mut self, if_statement: &ASTIfStatement) {
+        let Some(condition) = self.evaluate_condition(&if_statement.condition, &if_statement.if_keyword.span) else { return; };
+        if condition {
+            self.goto_statement(&if_statement.then_branch);
+        } else if let Some(else_branch) = &if_statement.else_branch {
+            self.goto_statement(&else_branch.else_statement);
+        }
+    }
+
+    fn goto_while_statement(&mut self, while_statement: &ASTWhileStatement) {
+        loop {
+            let Some(condition) = self.evaluate_condition(&while_statement.condition, &while_statement.while_keyword.span) else { return; };
+            if !condition || !self.take_step(&while_statement.while_keyword.span) {
+                break;
+            }
+            self.goto_statement(&while_statement.body);
+            match self.control_flow {
+                Some(ControlFlow::BREAK) => {
+                    self.control_flow = None;
+                    break;
+                }
+                Some(ControlFlow::CONTINUE) => self.control_flow = None,
+                Some(ControlFlow::RETURN) => break,
+                None if self.runtime_error.is_some() => break,
+                None => {}
+            }
+        }
+    }
+
+    fn goto_break_statement(&mut self, break_keyword: &Token) {
+        self.control_flow = Some(ControlFlow::BREAK);
+    }
+
+    fn goto_continue_statement(&mut self, continue_keyword: &Token) {
+        self.control_flow = Some(ControlFlow::CONTINUE);
+    }
+
+    //Functions are registered up front by declare_functions
+    fn goto_function_declaration(&mut self, function_declaration: &ASTFunctionDeclaration) { }
+
+    fn goto_return_statement(&mut self, return_statement: &ASTReturnStatement) {
+        self.last_value = None;
+        if let Some(return_value) = &return_statement.return_value {
+            self.goto_expression(return_value);
+            if self.expect_value(&return_statement.return_keyword.span).is_none() {
+                return;
+            }
+        }
+        self.control_flow = Some(ControlFlow::RETURN);
+    }
+
+    fn goto_call_expression(&mut self, call_expression: &CallExpression) {
+        if let Some(builtin) = BuiltinFunction::from_name(call_expression.identifier()) {
+            return self.call_builtin_function(builtin, call_expression);
+        }
+        let Some(function) = self.functions.get(call_expression.identifier()).cloned() else {
+            if let Some((return_type, native_function)) = self.native_functions.get(call_expression.identifier()).cloned() {
+                return self.call_native_function(return_type, native_function, call_expression);
+            }
+            self.report_runtime_error(format!("Undeclared function '{}'", call_expression.identifier()), &call_expression.identifier.span);
+            return;
+        };
+        let Some(arguments) = self.evaluate_arguments(call_expression) else { return; };
+        if self.call_depth == self.limits.max_call_depth {
+            self.report_runtime_error(format!("Maximum call depth of {} exceeded", self.limits.max_call_depth), &call_expression.identifier.span);
+            return;
+        }
+        if !self.take_step(&call_expression.identifier.span) {
+            return;
+        }
+
+        let caller_locals = self.variables.take_locals();
+        self.variables.enter_scope();
+        for (parameter, value) in function.parameters.iter().zip(arguments) {
+            self.variables.declare(parameter.identifier.span.literal.clone(), value);
+        }
+        self.call_depth += 1;
+        self.last_value = None;
+        self.goto_statement(&function.body);
+        self.call_depth -= 1;
+        self.variables.restore_locals(caller_locals);
+
+        //Falling off the end of the body returns nothing
+        if self.control_flow.take() != Some(ControlFlow::RETURN) {
+            self.last_value = None;
+        }
     }
 
     fn goto_variable_expression(&mut self, variable_expression: &VariableExpression) {
-        self.last_value = Some(*self.variables.get(&variable_expression.identifier.span.literal).unwrap());
+        self.last_value = self.variables.lookup(variable_expression.identifier()).cloned();
+        if self.last_value.is_none() {
+            self.report_runtime_error(format!("Undeclared variable '{}'", variable_expression.identifier()), &variable_expression.identifier.span);
+        }
+    }
+
+    fn goto_assignment_expression(&mut self, assignment_expression: &AssignmentExpression) {
+        let span = &assignment_expression.operator_token.span;
+        let value = match &assignment_expression.operator {
+            None => {
+                self.goto_expression(&assignment_expression.value);
+                let Some(value) = self.expect_value(span) else { return; };
+                value
+            }
+            //The variable is read before the value is evaluated, like the left operand of a binary expression
+            Some(operator) => {
+                self.last_value = self.variables.lookup(assignment_expression.identifier()).cloned();
+                let Some(left) = self.expect_value(span) else { return; };
+                self.goto_expression(&assignment_expression.value);
+                let Some(right) = self.expect_value(span) else { return; };
+                match evaluate_binary_operation(operator, left, right) {
+                    Ok(value) => value,
+                    Err(message) => return self.report_runtime_error(message, span),
+                }
+            }
+        };
+        let Some(variable) = self.variables.lookup_mut(assignment_expression.identifier()) else {
+            return self.report_runtime_error(format!("Undeclared variable '{}'", assignment_expression.identifier()), &assignment_expression.identifier.span);
+        };
+        *variable = value.clone();
+        self.last_value = Some(value);
     }
 
     fn goto_number_expression(&mut self, number: &NumberExpression) {
-        self.last_value = Some(number.number);
+        self.last_value = Some(Value::INTEGER(number.number));
+    }
+
+    fn goto_double_expression(&mut self, double: &DoubleExpression) {
+        self.last_value = Some(Value::DOUBLE(double.value));
+    }
+
+    fn goto_boolean_expression(&mut self, boolean: &BooleanExpression) {
+        self.last_value = Some(Value::BOOLEAN(boolean.value));
+    }
+
+    fn goto_string_expression(&mut self, string: &StringExpression) {
+        self.last_value = Some(Value::STRING(string.value.clone()));
+    }
+
+    fn goto_interpolation_expression(&mut self, interpolation: &InterpolationExpression) {
+        let mut text = String::new();
+        for part in &interpolation.parts {
+            match part {
+                InterpolationPart::TEXT(part_text) => text.push_str(part_text),
+                InterpolationPart::EXPRESSION(expression, span) => {
+                    self.goto_expression(expression);
+                    let Some(value) = self.expect_value(span) else { return; };
+                    text.push_str(&value.to_string());
+                }
+            }
+        }
+        self.last_value = Some(Value::STRING(text));
     }
 
     fn goto_error(&mut self, span: &SourceCodeSpan) {
-        // TODO
+        self.report_runtime_error("Cannot evaluate an invalid expression".to_string(), span);
     }
 
     fn goto_unary_expression(&mut self, unary_expression: &UnaryExpression) {
         self.goto_expression(&unary_expression.operand);
-        let operand = self.last_value.unwrap();
-        self.last_value = Some(match unary_expression.operator.kind {
-            UnaryOperatorType::MINUS => -operand,
-            UnaryOperatorType::NOT => !operand,
-        });
+        let span = &unary_expression.operator.token.span;
+        self.last_value = match unary_expression.operator.kind {
+            UnaryOperatorType::MINUS => match self.expect_value(span).map(evaluate_negation) {
+                Some(Ok(negated)) => Some(negated),
+                Some(Err(message)) => {
+                    self.report_runtime_error(message, span);
+                    None
+                }
+                None => None,
+            },
+            UnaryOperatorType::NOT => self.expect_integer(span).map(|operand| Value::INTEGER(!operand)),
+            UnaryOperatorType::LOGICALNOT => self.expect_boolean(span).map(|operand| Value::BOOLEAN(!operand)),
+        };
     }
 
     fn goto_binary_expression(&mut self, expr: &BinaryExpression) {
+        match expr.operator.kind {
+            BinaryOperatorType::LOGICALAND | BinaryOperatorType::LOGICALOR => return self.evaluate_logical_expression(expr),
+            BinaryOperatorType::EQUALS | BinaryOperatorType::NOTEQUALS => return self.evaluate_equality_expression(expr),
+            _ => {}
+        }
+        let span = &expr.operator.token.span;
         self.goto_expression(&expr.left);
-        let left = self.last_value.unwrap();
+        let Some(left) = self.expect_value(span) else { return; };
         self.goto_expression(&expr.right);
-        let right = self.last_value.unwrap();
-        self.last_value = Some(match expr.operator.kind {
-            BinaryOperatorType::PLUS => left + right,
-            BinaryOperatorType::MINUS => left - right,
-            BinaryOperatorType::MULTIPLY => left * right,
-            BinaryOperatorType::DIVIDE => left / right,
-            BinaryOperatorType::POWER => left.pow(right as u32),
-            BinaryOperatorType::AND => left & right,
-            BinaryOperatorType::OR => left | right,
-            BinaryOperatorType::XOR => left ^ right,
-        });
+        let Some(right) = self.expect_value(span) else { return; };
+        match evaluate_binary_operation(&expr.operator.kind, left, right) {
+            Ok(value) => self.last_value = Some(value),
+            Err(message) => self.report_runtime_error(message, span),
+        }
     }
 
     fn goto_parenthesized_expression(&mut self, parenthesized_expression: &ParenthesizedExpression) {
         self.goto_expression(&parenthesized_expression.expression);
     }
-}
\ No newline at end of file
+}
+
+#[cfg(test)]
+mod test {
+    use std::cell::RefCell;
+    use std::rc::Rc;
+    use crate::compilation_unit::CompilationUnit;
+    use crate::syntax_tree::evaluator::{ASTEvaluator, RuntimeError};
+    use crate::syntax_tree::value::Value;
+
+    fn evaluate(input: &str) -> Option<Value> {
+        let compilation_unit = CompilationUnit::compile(input);
+        assert!(!compilation_unit.diagnostics_vector.borrow().has_errors(), "Expected no errors, but got {:?}", compilation_unit.diagnostics_vector.borrow().diagnostics);
+        let result = ASTEvaluator::new().evaluate(&compilation_unit.ast);
+        return result.expect("Expected no runtime error");
+    }
+
+    fn evaluate_runtime_error(input: &str) -> RuntimeError {
+        let compilation_unit = CompilationUnit::compile(input);
+        assert!(!compilation_unit.diagnostics_vector.borrow().has_errors(), "Expected no errors, but got {:?}", compilation_unit.diagnostics_vector.borrow().diagnostics);
+        let result = ASTEvaluator::new().evaluate(&compilation_unit.ast);
+        return result.expect_err("Expected a runtime error");
+    }
+
+    #[test]
+    fn should_take_then_branch() {
+        assert_eq!(evaluate("let a = 2; if a > 1 { 10 } else { 20 }"), Some(Value::INTEGER(10)));
+    }
+
+    #[test]
+    fn should_take_else_if_branch() {
+        assert_eq!(evaluate("if false { 1 } else if 1 == 2 { 2 } else if 1 != 2 { 3 } else { 4 }"), Some(Value::INTEGER(3)));
+    }
+
+    #[test]
+    fn should_break_out_of_nested_blocks() {
+        let input = "
+            let a = 1
+            while a == 1 {
+                let a = 0
+                { if true { break } }
+                let b = 5
+            }
+            a
+        ";
+        assert_eq!(evaluate(input), Some(Value::INTEGER(1)));
+    }
+
+    #[test]
+    fn should_propagate_break_and_continue() {
+        let input = "
+            var i = 0
+            var sum = 0
+            while true {
+                i += 1
+                if i == 3 { continue }
+                { if i == 6 { break } }
+                sum += i
+            }
+            sum
+        ";
+        assert_eq!(evaluate(input), Some(Value::INTEGER(1 + 2 + 4 + 5)));
+    }
+
+    #[test]
+    fn should_not_produce_values_from_conditions() {
+        assert_eq!(evaluate("if 1 > 2 { 5 }"), None);
+        assert_eq!(evaluate("let a = 4; a; if a > 5 { 5 }"), Some(Value::INTEGER(4)));
+        assert_eq!(evaluate("var i = 0; while i < 3 { i += 1 }"), Some(Value::INTEGER(3)));
+    }
+
+    #[test]
+    fn should_shadow_variables_inside_blocks() {
+        assert_eq!(evaluate("let a = 1; { let a = a + 1; { let a = a * 10 } } a"), Some(Value::INTEGER(1)));
+        assert_eq!(evaluate("let a = 1; { let a = a + 1; { let a = a * 10; a } }"), Some(Value::INTEGER(20)));
+    }
+
+    #[test]
+    fn should_assign_to_mutable_variables() {
+        assert_eq!(evaluate("var total = 0; var i = 1; while i <= 10 { total += i; i += 1 } total"), Some(Value::INTEGER(55)));
+        assert_eq!(evaluate("var a = 1; var b = 2; { a = b = 5 } a + b"), Some(Value::INTEGER(10)));
+        assert_eq!(evaluate("var a = 5; a -= 1; a *= 3; a /= 2; a **= 2; a &= 60; a |= 1; a ^= 3"), Some(Value::INTEGER(38)));
+        assert_eq!(evaluate("var count = 0; func bump() { count += 1 } bump(); bump(); count"), Some(Value::INTEGER(2)));
+        assert_eq!(evaluate_runtime_error("var a = 1; a /= 0").message, "Division by zero");
+    }
+
+    #[test]
+    fn should_concatenate_and_compare_strings() {
+        assert_eq!(evaluate("let name = \"Astra\"; \"Hello, \" + name + \"!\""), Some(Value::STRING("Hello, Astra!".to_string())));
+        assert_eq!(evaluate("var s = \"a\"; s += \"b\"; s == \"ab\" && s != \"ba\""), Some(Value::BOOLEAN(true)));
+        assert_eq!(evaluate("func greet(who: String) -> String { return \"hi \" + who } greet(\"\\u{1F600}\")"), Some(Value::STRING("hi 😀".to_string())));
+        assert_eq!(evaluate_runtime_error("func id(x) { return x } \"a\" + id(1)").message, "Expected a value of type String, found Int");
+    }
+
+    #[test]
+    fn should_evaluate_doubles_and_numeric_literal_forms() {
+        assert_eq!(evaluate("let r: Double = 1.5; 2.0 * r ** 2.0 - -0.5"), Some(Value::DOUBLE(5.0)));
+        assert_eq!(evaluate("1.0 / 4e1 < 0.1_0 && 1.0 / 0.0 > 1e308"), Some(Value::BOOLEAN(true)));
+        assert_eq!(evaluate("0xFF + 0b1010 + 0o17 + 1_000"), Some(Value::INTEGER(1280)));
+        assert_eq!(evaluate("let a = -9_223_372_036_854_775_808; a"), Some(Value::INTEGER(i64::MIN)));
+        assert_eq!(evaluate_runtime_error("func id(x) { return x } 1.5 + id(1)").message, "Expected a value of type Double, found Int");
+    }
+
+    #[test]
+    fn should_print_interpolated_strings_to_the_output_sink() {
+        let compilation_unit = CompilationUnit::compile("
+            let name = \"Astra\"
+            var i = 1
+            while i <= 2 { println(\"\\(name) #\\(i):\", i * 2 == 2, \"\\(\"nested \\(i + 1)\")\"); i += 1 }
+            print(\"done\")
+            \"\\(i)\"
+        ");
+        assert!(!compilation_unit.has_errors(), "Expected no errors, but got {:?}", compilation_unit.diagnostics_vector.borrow().diagnostics);
+        let output = Rc::new(RefCell::new(String::new()));
+        let mut evaluator = ASTEvaluator::new();
+        evaluator.set_output(output.clone());
+        assert_eq!(evaluator.evaluate(&compilation_unit.ast), Ok(Some(Value::STRING("3".to_string()))));
+        assert_eq!(output.borrow().as_str(), "Astra #1: true nested 2\nAstra #2: false nested 3\ndone");
+        assert_eq!(evaluate_runtime_error("func f() { } println(1, f())").message, "Expression does not produce a value");
+        assert_eq!(evaluate_runtime_error("func f() { } \"\\(f())\"").span.literal, "f()");
+    }
+
+    #[test]
+    fn should_start_a_new_statement_at_a_line_starting_with_minus_or_parenthesis() {
+        assert_eq!(evaluate("let a = 5\n-1"), Some(Value::INTEGER(-1)));
+        assert_eq!(evaluate("let a = 5\n-1\na"), Some(Value::INTEGER(5)));
+        assert_eq!(evaluate("let a = 5\nlet b = a\n(2)\nb"), Some(Value::INTEGER(5)));
+        assert_eq!(evaluate("let a = 5\nlet b = (a\n- 1)\nb +\n1"), Some(Value::INTEGER(5)));
+    }
+
+    #[test]
+    fn should_call_functions_declared_later() {
+        let input = "
+            let a = add(1, 2)
+            func add(x, y) { return x + y }
+            a * 2
+        ";
+        assert_eq!(evaluate(input), Some(Value::INTEGER(6)));
+    }
+
+    #[test]
+    fn should_evaluate_recursive_functions() {
+        let input = "
+            func fib(n) {
+                if n <= 1 { return n }
+                return fib(n - 1) + fib(n - 2)
+            }
+            fib(15)
+        ";
+        assert_eq!(evaluate(input), Some(Value::INTEGER(610)));
+    }
+
+    #[test]
+    fn should_not_see_caller_locals() {
+        let input = "
+            let a = 1
+            func get() { return a }
+            { let a = 2; get() }
+        ";
+        assert_eq!(evaluate(input), Some(Value::INTEGER(1)));
+    }
+
+    #[test]
+    fn should_return_from_inside_loops() {
+        assert_eq!(evaluate("func f() { while true { { return 7 } } } f()"), Some(Value::INTEGER(7)));
+    }
+
+    #[test]
+    fn should_report_unbounded_recursion() {
+        assert_eq!(evaluate_runtime_error("func f(n) { return f(n + 1) } f(0)").message, "Maximum call depth of 200 exceeded");
+    }
+
+    #[test]
+    fn should_report_missing_return_value() {
+        assert_eq!(evaluate_runtime_error("func f() { } let a = f()").message, "Expression does not produce a value");
+    }
+
+    #[test]
+    fn should_evaluate_comparisons() {
+        assert_eq!(evaluate("1 + 2 * 3 >= 7 == true"), Some(Value::BOOLEAN(true)));
+        assert_eq!(evaluate("3 < 2 != 2 > 3"), Some(Value::BOOLEAN(false)));
+    }
+
+    #[test]
+    fn should_short_circuit_logical_operators() {
+        //Evaluating the right operand would exceed the call depth
+        let input = "
+            func loop() { return loop() }
+            let a = false && loop()
+            let b = true || loop()
+            !a && b
+        ";
+        assert_eq!(evaluate(input), Some(Value::BOOLEAN(true)));
+        assert_eq!(evaluate("1 < 2 && 2 < 3 || false"), Some(Value::BOOLEAN(true)));
+    }
+
+    #[test]
+    fn should_report_mismatched_operand_types() {
+        //Unannotated parameters are only checked at runtime
+        assert_eq!(evaluate_runtime_error("func id(x) { return x } 1 + id(true)").message, "Expected a value of type Int, found Bool");
+        assert_eq!(evaluate_runtime_error("func id(x) { return x } if id(1) { }").message, "Expected a value of type Bool, found Int");
+        assert_eq!(evaluate_runtime_error("func id(x) { return x } 1 == id(false)").message, "Cannot compare values of types Int and Bool");
+    }
+
+    #[test]
+    fn should_report_arithmetic_errors_at_the_operator() {
+        let error = evaluate_runtime_error("let a = 0; 1 + 10 / a");
+        assert_eq!(error.message, "Division by zero");
+        assert_eq!((error.span.start, error.span.literal.as_str()), (18, "/"));
+
+        assert_eq!(evaluate_runtime_error("2 ** 64").message, "Integer overflow");
+        assert_eq!(evaluate_runtime_error("2 ** (0 - 1)").message, "Negative exponent");
+        assert_eq!(evaluate_runtime_error("9223372036854775807 + 1").message, "Integer overflow");
+        assert_eq!(evaluate_runtime_error("let min = (0 - 9223372036854775807) - 1; { -min }").message, "Integer overflow");
+        assert_eq!(evaluate_runtime_error("let min = (0 - 9223372036854775807) - 1; { min / (0 - 1) }").message, "Integer overflow");
+    }
+
+    #[test]
+    fn should_truncate_division_toward_zero() {
+        assert_eq!(evaluate("let a = 7; a / 2 == 3 && -a / 2 == -3 && a / -2 == -3 && -a / -2 == 3"), Some(Value::BOOLEAN(true)));
+        //The remainder takes the sign of the dividend
+        assert_eq!(evaluate("let a = 7; a % 2 == 1 && -a % 2 == -1 && a % -2 == 1 && -a % -2 == -1"), Some(Value::BOOLEAN(true)));
+        assert_eq!(evaluate("let a = 7.5; a % 2.0 == 1.5 && -a % 2.0 == -1.5"), Some(Value::BOOLEAN(true)));
+        assert_eq!(evaluate("let a = -9223372036854775808; a % -1"), Some(Value::INTEGER(0)));
+    }
+
+    #[test]
+    fn should_shift_within_range() {
+        assert_eq!(evaluate("let a = 1; a << 63 >> 63"), Some(Value::INTEGER(-1)));
+        assert_eq!(evaluate("var a = -16; a >>= 2; a <<= 1; a %= 5"), Some(Value::INTEGER(-3)));
+        assert_eq!(evaluate_runtime_error("let a = 64; 1 << a").message, "Shift amount 64 is out of range");
+        assert_eq!(evaluate_runtime_error("let a = -1; 1 >> a").message, "Shift amount -1 is out of range");
+        assert_eq!(evaluate_runtime_error("let a = 0; 1 % a").message, "Division by zero");
+    }
+
+    #[test]
+    fn should_raise_powers_to_the_right() {
+        assert_eq!(evaluate("let a = 2; a ** 3 ** 2"), Some(Value::INTEGER(512)));
+        assert_eq!(evaluate("let a = 2; -a ** 2 == -4 && (-a) ** 2 == 4"), Some(Value::BOOLEAN(true)));
+        assert_eq!(evaluate("let a = 10; a - 3 - 2"), Some(Value::INTEGER(5)));
+    }
+
+    #[test]
+    fn should_raise_large_exponents_of_trivial_bases() {
+        assert_eq!(evaluate("1 ** 5000000000 + (0 - 1) ** 5000000001"), Some(Value::INTEGER(0)));
+    }
+
+    #[test]
+    fn should_stop_evaluating_after_runtime_error() {
+        let compilation_unit = CompilationUnit::compile("let a = 1 / 0; let b = 2");
+        let mut eval = ASTEvaluator::new();
+        assert!(eval.evaluate(&compilation_unit.ast).is_err());
+        assert!(eval.variables.lookup("b").is_none());
+    }
+}
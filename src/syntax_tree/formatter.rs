@@ -0,0 +1,373 @@
+use crate::syntax_tree::{AbstractSyntaxTree, AssignmentExpression, ASTStatement, Associativity, BinaryExpression, BinaryOperator, BinaryOperatorType, Expression, ExpressionType, BooleanExpression, InterpolationExpression, InterpolationPart, ASTLetStatement, ASTBlockStatement, ASTIfStatement, ASTWhileStatement, ASTFunctionDeclaration, ASTReturnStatement, CallExpression, DoubleExpression, NumberExpression, ParenthesizedExpression, StringExpression, UnaryExpression, VariableExpression, ASTTraverser, escape_string};
+use crate::syntax_tree::lexer::{SourceCodeSpan, Token};
+
+//Prints the tree back as Astra source with canonical spacing and indentation, parentheses are only kept
+//where the operator precedence needs them and comments are kept in front of or after the statements
+pub struct ASTFormatter {
+    indent: usize,
+    result: String,
+}
+
+impl ASTFormatter {
+    pub fn new() -> Self {
+        Self { indent: 0, result: String::new() }
+    }
+
+    pub fn format(ast: &AbstractSyntaxTree) -> String {
+        let mut formatter = ASTFormatter::new();
+        ast.visit(&mut formatter);
+        formatter.add_comment_lines(&ast.trailing_comments);
+        return formatter.result;
+    }
+
+    fn add_comment_lines(&mut self, comments: &[Token]) {
+        for comment in comments {
+            self.add_indent();
+            self.result.push_str(comment.span.literal.trim_end());
+            self.result.push('\n');
+        }
+    }
+
+    fn add_trailing_comments(&mut self, comments: &[Token]) {
+        for comment in comments {
+            self.add_space();
+            self.result.push_str(comment.span.literal.trim_end());
+        }
+    }
+
+    fn add_space(&mut self) { self.result.push(' '); }
+
+    fn add_indent(&mut self) { self.result.push_str(&"    ".repeat(self.indent)); }
+
+    fn add_type_annotation(&mut self, type_annotation: &Option<Token>) {
+        if let Some(type_annotation) = type_annotation {
+            self.result.push_str(": ");
+            self.result.push_str(&type_annotation.span.literal);
+        }
+    }
+
+    fn without_parentheses(expression: &Expression) -> &Expression {
+        return match &expression.kind {
+            ExpressionType::PARENTHESIZED(parenthesized) => Self::without_parentheses(&parenthesized.expression),
+            _ => expression,
+        };
+    }
+
+    //An operand of the same precedence needs parentheses on the side its operator does not group to,
+    //assignments bind looser than any operator
+    fn needs_parentheses(operand: &Expression, operator: &BinaryOperator, is_right_operand: bool) -> bool {
+        return match &operand.kind {
+            ExpressionType::ASSIGNMENT(_) => true,
+            //"-a ** b" would only raise "a"
+            ExpressionType::UNARY(_) => !is_right_operand && operator.precedence() >= BinaryOperatorType::POWER.precedence(),
+            ExpressionType::BINARY(operand) if operand.operator.precedence() == operator.precedence() => match operator.associativity() {
+                Associativity::LEFT => is_right_operand,
+                Associativity::RIGHT => !is_right_operand,
+            },
+            ExpressionType::BINARY(operand) => operand.operator.precedence() < operator.precedence(),
+            _ => false,
+        };
+    }
+
+    fn add_operand(&mut self, operand: &Expression, needs_parentheses: bool) {
+        if needs_parentheses {
+            self.result.push('(');
+            self.goto_expression(operand);
+            self.result.push(')');
+        } else {
+            self.goto_expression(operand);
+        }
+    }
+}
+
+impl ASTTraverser for ASTFormatter {
+    fn goto_statement(&mut self, statement: &ASTStatement) {
+        self.add_comment_lines(&statement.leading_comments);
+        self.add_indent();
+        self.do_visit_statement(statement);
+        self.add_trailing_comments(&statement.trailing_comments);
+        self.result.push('\n');
+    }
+
+    fn goto_let_statement(&mut self, let_statement: &ASTLetStatement) {
+        self.result.push_str(&let_statement.keyword.span.literal);
+        self.add_space();
+        self.result.push_str(&let_statement.identifier.span.literal);
+        self.add_type_annotation(&let_statement.type_annotation);
+        self.result.push_str(" = ");
+        self.goto_expression(&let_statement.initializer);
+    }
+
+    fn goto_block_statement(&mut self, block_statement: &ASTBlockStatement) {
+        if block_statement.statements.is_empty() && block_statement.closing_comments.is_empty() {
+            self.result.push_str("{ }");
+            return;
+        }
+        self.result.push_str("{\n");
+        self.indent += 1;
+        for statement in &block_statement.statements {
+            self.goto_statement(statement);
+        }
+        self.add_comment_lines(&block_statement.closing_comments);
+        self.indent -= 1;
+        self.add_indent();
+        self.result.push('}');
+    }
+
+    fn goto_if_statement(&mut self, if_statement: &ASTIfStatement) {
+        self.result.push_str("if ");
+        self.goto_expression(&if_statement.condition);
+        self.add_space();
+        //Nested statements continue the current line, goto_statement would start a new one
+        self.do_visit_statement(&if_statement.then_branch);
+        if let Some(else_branch) = &if_statement.else_branch {
+            self.result.push_str(" else ");
+            self.do_visit_statement(&else_branch.else_statement);
+        }
+    }
+
+    fn goto_while_statement(&mut self, while_statement: &ASTWhileStatement) {
+        self.result.push_str("while ");
+        self.goto_expression(&while_statement.condition);
+        self.add_space();
+        self.do_visit_statement(&while_statement.body);
+    }
+
+    fn goto_break_statement(&mut self, break_keyword: &Token) {
+        self.result.push_str("break");
+    }
+
+    fn goto_continue_statement(&mut self, continue_keyword: &Token) {
+        self.result.push_str("continue");
+    }
+
+    fn goto_function_declaration(&mut self, function_declaration: &ASTFunctionDeclaration) {
+        self.result.push_str("func ");
+        self.result.push_str(function_declaration.identifier());
+        self.result.push('(');
+        for (index, parameter) in function_declaration.parameters.iter().enumerate() {
+            if index > 0 {
+                self.result.push_str(", ");
+            }
+            self.result.push_str(&parameter.identifier.span.literal);
+            self.add_type_annotation(&parameter.type_annotation);
+        }
+        self.result.push_str(") ");
+        if let Some(return_type) = &function_declaration.return_type {
+            self.result.push_str("-> ");
+            self.result.push_str(&return_type.span.literal);
+            self.add_space();
+        }
+        self.do_visit_statement(&function_declaration.body);
+    }
+
+    fn goto_return_statement(&mut self, return_statement: &ASTReturnStatement) {
+        self.result.push_str("return");
+        if let Some(return_value) = &return_statement.return_value {
+            self.add_space();
+            self.goto_expression(return_value);
+        }
+    }
+
+    fn goto_variable_expression(&mut self, variable_expression: &VariableExpression) {
+        self.result.push_str(variable_expression.identifier());
+    }
+
+    fn goto_number_expression(&mut self, number: &NumberExpression) {
+        self.result.push_str(&number.literal);
+    }
+
+    fn goto_double_expression(&mut self, double: &DoubleExpression) {
+        self.result.push_str(&double.literal);
+    }
+
+    fn goto_boolean_expression(&mut self, boolean: &BooleanExpression) {
+        self.result.push_str(&boolean.value.to_string());
+    }
+
+    fn goto_string_expression(&mut self, string: &StringExpression) {
+        self.result.push_str(&string.literal());
+    }
+
+    fn goto_interpolation_expression(&mut self, interpolation: &InterpolationExpression) {
+        self.result.push('"');
+        for part in &interpolation.parts {
+            match part {
+                InterpolationPart::TEXT(text) => self.result.push_str(&escape_string(text)),
+                InterpolationPart::EXPRESSION(expression, _) => {
+                    self.result.push_str("\\(");
+                    self.goto_expression(Self::without_parentheses(expression));
+                    self.result.push(')');
+                }
+            }
+        }
+        self.result.push('"');
+    }
+
+    fn goto_error(&mut self, span: &SourceCodeSpan) {
+        self.result.push_str(&span.literal);
+    }
+
+    fn goto_unary_expression(&mut self, unary_expression: &UnaryExpression) {
+        self.result.push_str(&unary_expression.operator.token.span.literal);
+        //Unary operators bind tighter than any binary operator but "**"
+        let operand = Self::without_parentheses(&unary_expression.operand);
+        let needs_parentheses = match &operand.kind {
+            ExpressionType::BINARY(operand) => operand.operator.precedence() < BinaryOperatorType::POWER.precedence(),
+            //Adjacent prefix operators like "--1" would read as a decrement
+            ExpressionType::ASSIGNMENT(_) | ExpressionType::UNARY(_) => true,
+            _ => false,
+        };
+        self.add_operand(operand, needs_parentheses);
+    }
+
+    fn goto_binary_expression(&mut self, binary_expression: &BinaryExpression) {
+        let operator = &binary_expression.operator;
+        let left = Self::without_parentheses(&binary_expression.left);
+        self.add_operand(left, Self::needs_parentheses(left, operator, false));
+        self.add_space();
+        self.result.push_str(&operator.token.span.literal);
+        self.add_space();
+        let right = Self::without_parentheses(&binary_expression.right);
+        self.add_operand(right, Self::needs_parentheses(right, operator, true));
+    }
+
+    //The enclosing expression adds the parentheses back when they are needed
+    fn goto_parenthesized_expression(&mut self, parenthesized_expression: &ParenthesizedExpression) {
+        self.goto_expression(&parenthesized_expression.expression);
+    }
+
+    fn goto_assignment_expression(&mut self, assignment_expression: &AssignmentExpression) {
+        self.result.push_str(assignment_expression.identifier());
+        self.add_space();
+        self.result.push_str(&assignment_expression.operator_token.span.literal);
+        self.add_space();
+        self.goto_expression(Self::without_parentheses(&assignment_expression.value));
+    }
+
+    fn goto_call_expression(&mut self, call_expression: &CallExpression) {
+        self.result.push_str(call_expression.identifier());
+        self.result.push('(');
+        for (index, argument) in call_expression.arguments.iter().enumerate() {
+            if index > 0 {
+                self.result.push_str(", ");
+            }
+            self.goto_expression(argument);
+        }
+        self.result.push(')');
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use crate::compilation_unit::CompilationUnit;
+    use crate::syntax_tree::formatter::ASTFormatter;
+
+    fn format(input: &str) -> String {
+        let compilation_unit = CompilationUnit::parse(input);
+        assert!(!compilation_unit.diagnostics_vector.borrow().has_errors(), "Expected no errors, but got {:?}", compilation_unit.diagnostics_vector.borrow().diagnostics);
+        return ASTFormatter::format(&compilation_unit.ast);
+    }
+
+    #[test]
+    fn should_format_with_canonical_spacing() {
+        let input = "func  add(x:Int,y)->Int{return x+y}   let a:Int=add( 1,2 );if a>=3{while false{break}}else if !true{ }else{-a}";
+        let expected = "\
+func add(x: Int, y) -> Int {
+    return x + y
+}
+let a: Int = add(1, 2)
+if a >= 3 {
+    while false {
+        break
+    }
+} else if !true { } else {
+    -a
+}
+";
+        assert_eq!(format(input), expected);
+    }
+
+    #[test]
+    fn should_keep_only_needed_parentheses() {
+        assert_eq!(format("((1 + 2)) * 3"), "(1 + 2) * 3\n");
+        assert_eq!(format("(1 * 2) + (3)"), "1 * 2 + 3\n");
+        assert_eq!(format("-(a + 1) == (-(a))"), "-(a + 1) == -a\n");
+        assert_eq!(format("-(-1) + !(!true) + -((-1 ** 2))"), "-(-1) + !(!true) + -(-1 ** 2)\n");
+        assert_eq!(format("a - (b - c)"), "a - (b - c)\n");
+        assert_eq!(format("(a - b) - c"), "a - b - c\n");
+        assert_eq!(format("(a ** b) ** c"), "(a ** b) ** c\n");
+        assert_eq!(format("a ** (b ** c)"), "a ** b ** c\n");
+        assert_eq!(format("(-a) ** 2 + -(a ** 2) + 2 ** (-a)"), "(-a) ** 2 + -a ** 2 + 2 ** -a\n");
+        assert_eq!(format("let x = (a || b) && !(c)"), "let x = (a || b) && !c\n");
+    }
+
+    #[test]
+    fn should_preserve_meaning_when_removing_parentheses() {
+        let input = "let a = 7; let b = ((a - 2) - 1) * (2 ** (1 + 1)) - (a / (7 - 6)); (a - 2) - 1";
+        let formatted = format(input);
+        assert_eq!(formatted, "let a = 7\nlet b = (a - 2 - 1) * 2 ** (1 + 1) - a / (7 - 6)\na - 2 - 1\n");
+        assert_eq!(crate::evaluate(&formatted), crate::evaluate(input));
+    }
+
+    #[test]
+    fn should_format_assignments() {
+        assert_eq!(format("var  a=1;let b=2; a+=b ;a=(a=b)"), "var a = 1\nlet b = 2\na += b\na = a = b\n");
+        assert_eq!(format("var a = 1; let b = (a = 2) + -(a *= 3)"), "var a = 1\nlet b = (a = 2) + -(a *= 3)\n");
+    }
+
+    #[test]
+    fn should_format_strings_with_canonical_escapes() {
+        assert_eq!(format("let s = \"a\\u{41}\\t\\\"b\\\"\"+\"\\u{7}é\""), "let s = \"aA\\t\\\"b\\\"\" + \"\\u{7}é\"\n");
+    }
+
+    #[test]
+    fn should_format_interpolations() {
+        assert_eq!(format("println( \"a\\((x+1))\\t\\(\"b\\( y )\")\" ,2)"), "println(\"a\\(x + 1)\\t\\(\"b\\(y)\")\", 2)\n");
+    }
+
+    #[test]
+    fn should_keep_numeric_literals_as_written() {
+        assert_eq!(format("let mask=0xFF_FF&0b1010 ;let x=1e-9*2.50"), "let mask = 0xFF_FF & 0b1010\nlet x = 1e-9 * 2.50\n");
+    }
+
+    #[test]
+    fn should_preserve_comments() {
+        let input = "\
+// Header
+/* A nested /* block */ comment */
+func f(x) { // Doubles
+  return x*2 // Result
+  // End of body
+}
+if true { } else {
+/* Empty */ }
+let a = 1 + /* inline */ 2 // One
+// Trailing";
+        let expected = "\
+// Header
+/* A nested /* block */ comment */
+func f(x) {
+    // Doubles
+    return x * 2 // Result
+    // End of body
+}
+if true { } else {
+    /* Empty */
+}
+/* inline */
+let a = 1 + 2 // One
+// Trailing
+";
+        let formatted = format(input);
+        assert_eq!(formatted, expected);
+        assert_eq!(format(&formatted), formatted);
+    }
+
+    #[test]
+    fn should_be_idempotent() {
+        let input = "let a = (1 + 2) * 3; func f(n) { if n <= 1 { return } f(n - 1) } f(a)";
+        let formatted = format(input);
+        assert_eq!(format(&formatted), formatted);
+    }
+}
@@ -3,49 +3,158 @@ use std::fmt::{Display, Formatter};
 #[derive(Debug, PartialEq, Clone)]
 pub enum TokenType {
     NUMERAL(i64),
+    DOUBLE(f64),
+    //Integer literal that does not fit in 64 bits
+    OVERFLOWINGNUMERAL,
+    //Floating-point literal too large to be anything but an infinity, like "1e999"
+    OVERFLOWINGDOUBLE,
+    //Numeric literal with a digit its base does not have or without any digits, like "0b12" or "0x"
+    INVALIDNUMERAL,
+    STRING,
+    //String literal without its closing quote, it ends with the line
+    UNTERMINATEDSTRING,
     // OPERATORS
     PLUS,
     MINUS,
     ASTERISK,
     SLASH,
+    PERCENT,
     EQUALS,
     AMPERSAND,
     PIPE,
     CARET,
     POWER,
+    SHIFTLEFT,
+    SHIFTRIGHT,
     NOT,
+    BANG,
+    EQUALSEQUALS,
+    BANGEQUALS,
+    LESSTHAN,
+    LESSTHANEQUALS,
+    GREATERTHAN,
+    GREATERTHANEQUALS,
+    DOUBLEAMPERSAND,
+    DOUBLEPIPE,
+    // ASSIGNMENT OPERATORS
+    PLUSEQUALS,
+    MINUSEQUALS,
+    ASTERISKEQUALS,
+    SLASHEQUALS,
+    PERCENTEQUALS,
+    POWEREQUALS,
+    SHIFTLEFTEQUALS,
+    SHIFTRIGHTEQUALS,
+    AMPERSANDEQUALS,
+    PIPEEQUALS,
+    CARETEQUALS,
     // KEYWORDS
     LET,
+    VAR,
+    IF,
+    ELSE,
+    WHILE,
+    BREAK,
+    CONTINUE,
+    FUNC,
+    RETURN,
+    TRUE,
+    FALSE,
     // OTHER
     LEFTPAR,
     RIGHTPAR,
+    LEFTBRACE,
+    RIGHTBRACE,
+    COMMA,
+    COLON,
+    SEMICOLON,
+    ARROW,
+    // TRIVIA
     WHITESPACE,
+    COMMENT,
+    //Block comment that is still open at the end of the input
+    UNTERMINATEDCOMMENT,
     IDENTIFIER,
     EOF,
     INVALID,
 }
 
+impl TokenType {
+    //Trivia does not take part in parsing
+    pub fn is_trivia(&self) -> bool {
+        return matches!(self, TokenType::WHITESPACE | TokenType::COMMENT | TokenType::UNTERMINATEDCOMMENT);
+    }
+}
+
 impl Display for TokenType {
     fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
         match self {
             TokenType::NUMERAL(_) => write!(f, "NUMERAL"),
+            TokenType::DOUBLE(_) => write!(f, "DOUBLE"),
+            TokenType::OVERFLOWINGNUMERAL => write!(f, "OVERFLOWINGNUMERAL"),
+            TokenType::OVERFLOWINGDOUBLE => write!(f, "OVERFLOWINGDOUBLE"),
+            TokenType::INVALIDNUMERAL => write!(f, "INVALIDNUMERAL"),
+            TokenType::STRING => write!(f, "STRING"),
+            TokenType::UNTERMINATEDSTRING => write!(f, "UNTERMINATEDSTRING"),
 
             TokenType::PLUS => write!(f, "+"),
             TokenType::MINUS => write!(f, "-"),
             TokenType::ASTERISK => write!(f, "*"),
             TokenType::SLASH => write!(f, "/"),
+            TokenType::PERCENT => write!(f, "%"),
             TokenType::EQUALS => write!(f, "="),
             TokenType::AMPERSAND => write!(f, "&"),
             TokenType::PIPE => write!(f, "|"),
             TokenType::CARET => write!(f, "^"),
             TokenType::POWER => write!(f, "**"),
+            TokenType::SHIFTLEFT => write!(f, "<<"),
+            TokenType::SHIFTRIGHT => write!(f, ">>"),
             TokenType::NOT => write!(f, "~"),
+            TokenType::BANG => write!(f, "!"),
+            TokenType::EQUALSEQUALS => write!(f, "=="),
+            TokenType::BANGEQUALS => write!(f, "!="),
+            TokenType::LESSTHAN => write!(f, "<"),
+            TokenType::LESSTHANEQUALS => write!(f, "<="),
+            TokenType::GREATERTHAN => write!(f, ">"),
+            TokenType::GREATERTHANEQUALS => write!(f, ">="),
+            TokenType::DOUBLEAMPERSAND => write!(f, "&&"),
+            TokenType::DOUBLEPIPE => write!(f, "||"),
+
+            TokenType::PLUSEQUALS => write!(f, "+="),
+            TokenType::MINUSEQUALS => write!(f, "-="),
+            TokenType::ASTERISKEQUALS => write!(f, "*="),
+            TokenType::SLASHEQUALS => write!(f, "/="),
+            TokenType::PERCENTEQUALS => write!(f, "%="),
+            TokenType::POWEREQUALS => write!(f, "**="),
+            TokenType::SHIFTLEFTEQUALS => write!(f, "<<="),
+            TokenType::SHIFTRIGHTEQUALS => write!(f, ">>="),
+            TokenType::AMPERSANDEQUALS => write!(f, "&="),
+            TokenType::PIPEEQUALS => write!(f, "|="),
+            TokenType::CARETEQUALS => write!(f, "^="),
 
             TokenType::LET => write!(f, "LET"),
+            TokenType::VAR => write!(f, "VAR"),
+            TokenType::IF => write!(f, "IF"),
+            TokenType::ELSE => write!(f, "ELSE"),
+            TokenType::WHILE => write!(f, "WHILE"),
+            TokenType::BREAK => write!(f, "BREAK"),
+            TokenType::CONTINUE => write!(f, "CONTINUE"),
+            TokenType::FUNC => write!(f, "FUNC"),
+            TokenType::RETURN => write!(f, "RETURN"),
+            TokenType::TRUE => write!(f, "TRUE"),
+            TokenType::FALSE => write!(f, "FALSE"),
 
             TokenType::LEFTPAR => write!(f, "("),
             TokenType::RIGHTPAR => write!(f, ")"),
+            TokenType::LEFTBRACE => write!(f, "{{"),
+            TokenType::RIGHTBRACE => write!(f, "}}"),
+            TokenType::COMMA => write!(f, ","),
+            TokenType::COLON => write!(f, ":"),
+            TokenType::SEMICOLON => write!(f, ";"),
+            TokenType::ARROW => write!(f, "->"),
             TokenType::WHITESPACE => write!(f, "WHITESPACE"),
+            TokenType::COMMENT => write!(f, "COMMENT"),
+            TokenType::UNTERMINATEDCOMMENT => write!(f, "UNTERMINATEDCOMMENT"),
             TokenType::IDENTIFIER => write!(f, "IDENTIFIER"),
             TokenType::EOF => write!(f, "EOF"),
             TokenType::INVALID => write!(f, "INVALID"),
@@ -55,9 +164,9 @@ impl Display for TokenType {
 
 #[derive(Debug, PartialEq, Clone)]
 pub struct SourceCodeSpan {
-    pub(crate) start: usize,
-    pub(crate) end: usize,
-    pub(crate) literal: String,
+    pub start: usize,
+    pub end: usize,
+    pub literal: String,
 }
 
 impl SourceCodeSpan {
@@ -68,13 +177,17 @@ impl SourceCodeSpan {
 
 #[derive(Debug, PartialEq, Clone)]
 pub struct Token {
-    pub(crate) kind: TokenType,
-    pub(crate) span: SourceCodeSpan,
+    pub kind: TokenType,
+    pub span: SourceCodeSpan,
+    //Comments on the lines before the token, filled in by the parser
+    pub leading_comments: Vec<Token>,
+    //Comments after the token on the same line, filled in by the parser
+    pub trailing_comments: Vec<Token>,
 }
 
 impl Token {
     pub fn new(kind: TokenType, span: SourceCodeSpan) -> Self {
-        Self { kind, span }
+        Self { kind, span, leading_comments: Vec::new(), trailing_comments: Vec::new() }
     }
 }
 
@@ -90,11 +203,11 @@ impl<'a> Lexer<'a> {
 
     pub fn next_token(&mut self) -> Option<Token> {
         if self.current_pos == self.input.len() {
-            let eof_indicator: char = '\0';
             self.current_pos += 1;
+            //The EOF token spans the (empty) end of the input so diagnostics can point at it
             return Some(Token::new(
                 TokenType::EOF,
-                SourceCodeSpan::new(0, 0, eof_indicator.to_string()),
+                SourceCodeSpan::new(self.input.len(), self.input.len(), String::new()),
             ));
         }
         let c = self.current_char();
@@ -102,18 +215,29 @@ impl<'a> Lexer<'a> {
             let start = self.current_pos;
             let kind;
             if Lexer::is_number_start(&c) {
-                let number: i64 = self.consume_number();
-                kind = TokenType::NUMERAL(number);
+                kind = self.consume_number();
             } 
+            else if c == '"' {
+                kind = self.consume_string();
+            }
             else if Lexer::is_whitespace(&c){
                 self.consume_token();
                 kind = TokenType::WHITESPACE;
             } 
-            //For simplicity and testing purposes both "let" and "var" are lexed (converted) as "let"
             else if Lexer::is_identifier_start(&c){
                 let identifier = self.consume_identifier();
                 kind = match identifier.as_str() {
-                    "let" | "var" => TokenType::LET,
+                    "let" => TokenType::LET,
+                    "var" => TokenType::VAR,
+                    "if" => TokenType::IF,
+                    "else" => TokenType::ELSE,
+                    "while" => TokenType::WHILE,
+                    "break" => TokenType::BREAK,
+                    "continue" => TokenType::CONTINUE,
+                    "func" => TokenType::FUNC,
+                    "return" => TokenType::RETURN,
+                    "true" => TokenType::TRUE,
+                    "false" => TokenType::FALSE,
                     _ => TokenType::IDENTIFIER,
                 }
 
@@ -130,33 +254,115 @@ impl<'a> Lexer<'a> {
     fn consume_symbol(&mut self) -> TokenType {
         let c = self.consume_token().unwrap();
         return match c {
-            '+' => TokenType::PLUS,
-            '-' => TokenType::MINUS,
+            '+' => if self.consume_if_next('=') { TokenType::PLUSEQUALS } else { TokenType::PLUS },
+            '-' if self.consume_if_next('>') => TokenType::ARROW,
+            '-' => if self.consume_if_next('=') { TokenType::MINUSEQUALS } else { TokenType::MINUS },
             //Decide if this is a power operator or a multiplication operator
-            '*' => {
-                if let Some(next) = self.current_char() {
-                    if next == '*' {
-                        self.consume_token();
-                        TokenType::POWER
-                    } 
-                    else { TokenType::ASTERISK }
-                    
-                } else {
-                    TokenType::ASTERISK
-                }
-            },
-            '/' => TokenType::SLASH,
+            '*' if self.consume_if_next('*') => if self.consume_if_next('=') { TokenType::POWEREQUALS } else { TokenType::POWER },
+            '*' => if self.consume_if_next('=') { TokenType::ASTERISKEQUALS } else { TokenType::ASTERISK },
+            '/' if self.consume_if_next('/') => self.consume_line_comment(),
+            '/' if self.consume_if_next('*') => self.consume_block_comment(),
+            '/' => if self.consume_if_next('=') { TokenType::SLASHEQUALS } else { TokenType::SLASH },
+            '%' => if self.consume_if_next('=') { TokenType::PERCENTEQUALS } else { TokenType::PERCENT },
             '(' => TokenType::LEFTPAR,
             ')' => TokenType::RIGHTPAR,
-            '=' => TokenType::EQUALS,
-            '&' => TokenType::AMPERSAND,
-            '|' => TokenType::PIPE,
-            '^' => TokenType::CARET,
+            '{' => TokenType::LEFTBRACE,
+            '}' => TokenType::RIGHTBRACE,
+            ',' => TokenType::COMMA,
+            ':' => TokenType::COLON,
+            ';' => TokenType::SEMICOLON,
+            '=' => if self.consume_if_next('=') { TokenType::EQUALSEQUALS } else { TokenType::EQUALS },
+            '!' => if self.consume_if_next('=') { TokenType::BANGEQUALS } else { TokenType::BANG },
+            '<' if self.consume_if_next('<') => if self.consume_if_next('=') { TokenType::SHIFTLEFTEQUALS } else { TokenType::SHIFTLEFT },
+            '<' => if self.consume_if_next('=') { TokenType::LESSTHANEQUALS } else { TokenType::LESSTHAN },
+            '>' if self.consume_if_next('>') => if self.consume_if_next('=') { TokenType::SHIFTRIGHTEQUALS } else { TokenType::SHIFTRIGHT },
+            '>' => if self.consume_if_next('=') { TokenType::GREATERTHANEQUALS } else { TokenType::GREATERTHAN },
+            '&' if self.consume_if_next('&') => TokenType::DOUBLEAMPERSAND,
+            '&' => if self.consume_if_next('=') { TokenType::AMPERSANDEQUALS } else { TokenType::AMPERSAND },
+            '|' if self.consume_if_next('|') => TokenType::DOUBLEPIPE,
+            '|' => if self.consume_if_next('=') { TokenType::PIPEEQUALS } else { TokenType::PIPE },
+            '^' => if self.consume_if_next('=') { TokenType::CARETEQUALS } else { TokenType::CARET },
             '~' => TokenType::NOT,
             _ => TokenType::INVALID,
         };
     }
 
+    //Consumes the next character only if it completes a two character operator
+    fn consume_if_next(&mut self, expected: char) -> bool {
+        if self.current_char() == Some(expected) {
+            self.consume_token();
+            return true;
+        }
+        return false;
+    }
+
+    //The comment ends before the line break, which is lexed as whitespace
+    fn consume_line_comment(&mut self) -> TokenType {
+        while self.current_char().is_some_and(|c| c != '\n') {
+            self.consume_token();
+        }
+        return TokenType::COMMENT;
+    }
+
+    //Block comments nest, so every "/*" inside needs its own "*/"
+    fn consume_block_comment(&mut self) -> TokenType {
+        let mut depth = 1;
+        while let Some(c) = self.consume_token() {
+            if c == '/' && self.consume_if_next('*') {
+                depth += 1;
+            } else if c == '*' && self.consume_if_next('/') {
+                depth -= 1;
+                if depth == 0 {
+                    return TokenType::COMMENT;
+                }
+            }
+        }
+        return TokenType::UNTERMINATEDCOMMENT;
+    }
+
+    //Escape sequences are only skipped here so the closing quote is found, they are decoded by decode_string
+    fn consume_string(&mut self) -> TokenType {
+        self.consume_token();
+        while let Some(c) = self.current_char() {
+            match c {
+                '\n' => break,
+                '"' => {
+                    self.consume_token();
+                    return TokenType::STRING;
+                }
+                '\\' if self.peek_char(1) == Some('(') => {
+                    let (length, _) = Self::scan_interpolation(&self.input[self.current_pos + 2..]);
+                    self.current_pos += 2 + length;
+                }
+                '\\' => {
+                    self.consume_token();
+                    if self.current_char().is_some_and(|c| c != '\n') {
+                        self.consume_token();
+                    }
+                }
+                _ => {
+                    self.consume_token();
+                }
+            }
+        }
+        return TokenType::UNTERMINATEDSTRING;
+    }
+
+    //Length of the expression of an interpolation up to and including its closing parenthesis, and whether that
+    //parenthesis was found before the end of the line, strings and parentheses nested in the expression are skipped
+    fn scan_interpolation(input: &str) -> (usize, bool) {
+        let mut lexer = Lexer::new(input);
+        let mut depth = 0;
+        while lexer.current_char().is_some_and(|c| c != '\n') {
+            match lexer.next_token().map(|token| token.kind) {
+                Some(TokenType::LEFTPAR) => depth += 1,
+                Some(TokenType::RIGHTPAR) if depth == 0 => return (lexer.current_pos, true),
+                Some(TokenType::RIGHTPAR) => depth -= 1,
+                _ => {}
+            }
+        }
+        return (lexer.current_pos, false);
+    }
 
     fn consume_identifier(&mut self) -> String {
         let mut identifier = String::new();
@@ -172,33 +378,293 @@ impl<'a> Lexer<'a> {
         return identifier;
     }
 
-    fn consume_number(&mut self) -> i64 {
-        let mut number: i64 = 0;
+    //Integers can also be written in hexadecimal, binary or octal after a "0x", "0b" or "0o" prefix, a fraction
+    //or an exponent makes the literal a double and underscores may separate the digits of any of them
+    fn consume_number(&mut self) -> TokenType {
+        if self.current_char() == Some('0') && let Some(radix) = self.peek_char(1).and_then(Self::radix_of_prefix) {
+            self.consume_token();
+            self.consume_token();
+            //Letters are consumed as well so a wrong digit is reported along with the rest of the literal
+            let digits = self.consume_digits(|c| c.is_ascii_alphanumeric());
+            return Self::integer_token(&digits, radix);
+        }
+        let mut literal = self.consume_digits(|c| c.is_ascii_digit());
+        let mut is_double = false;
+        if self.current_char() == Some('.') && self.peek_char(1).is_some_and(|c| c.is_ascii_digit()) {
+            self.consume_token();
+            literal.push('.');
+            literal.push_str(&self.consume_digits(|c| c.is_ascii_digit()));
+            is_double = true;
+        }
+        let has_exponent = match (self.current_char(), self.peek_char(1), self.peek_char(2)) {
+            (Some('e' | 'E'), Some('+' | '-'), Some(digit)) | (Some('e' | 'E'), Some(digit), _) => digit.is_ascii_digit(),
+            _ => false,
+        };
+        if has_exponent {
+            literal.push(self.consume_token().unwrap());
+            if matches!(self.current_char(), Some('+' | '-')) {
+                literal.push(self.consume_token().unwrap());
+            }
+            literal.push_str(&self.consume_digits(|c| c.is_ascii_digit()));
+            is_double = true;
+        }
+        if is_double {
+            return match literal.parse::<f64>() {
+                Ok(value) if value.is_infinite() => TokenType::OVERFLOWINGDOUBLE,
+                Ok(value) => TokenType::DOUBLE(value),
+                Err(_) => TokenType::INVALIDNUMERAL,
+            };
+        }
+        return Self::integer_token(&literal, 10);
+    }
+
+    fn radix_of_prefix(c: char) -> Option<u32> {
+        return match c {
+            'x' | 'X' => Some(16),
+            'b' | 'B' => Some(2),
+            'o' | 'O' => Some(8),
+            _ => None,
+        };
+    }
+
+    //Consumes the digits along with the underscores between them, which are left out of the result
+    fn consume_digits(&mut self, is_digit: impl Fn(char) -> bool) -> String {
+        let mut digits = String::new();
         while let Some(c) = self.current_char() {
-            if c.is_digit(10) {
-                self.consume_token().unwrap();
-                number = number * 10 + c.to_digit(10).unwrap() as i64;
-            } else {
+            if c != '_' && !is_digit(c) {
                 break;
             }
+            self.consume_token();
+            if c != '_' {
+                digits.push(c);
+            }
         }
+        return digits;
+    }
 
-        return number;
+    //Value of "-literal" for an overflowing literal, only i64::MIN has a magnitude that does not fit on its own
+    pub fn negated_integer(literal: &str) -> Option<i64> {
+        let digits: String = literal.chars().filter(|c| *c != '_').collect();
+        let mut chars = digits.chars();
+        let (digits, radix) = match (chars.next(), chars.next().and_then(Self::radix_of_prefix)) {
+            (Some('0'), Some(radix)) => (&digits[2..], radix),
+            _ => (digits.as_str(), 10),
+        };
+        let magnitude = i128::from_str_radix(digits, radix).ok()?;
+        return i64::try_from(-magnitude).ok();
+    }
+
+    fn integer_token(digits: &str, radix: u32) -> TokenType {
+        if digits.is_empty() || !digits.chars().all(|c| c.is_digit(radix)) {
+            return TokenType::INVALIDNUMERAL;
+        }
+        return match i64::from_str_radix(digits, radix) {
+            Ok(number) => TokenType::NUMERAL(number),
+            Err(_) => TokenType::OVERFLOWINGNUMERAL,
+        };
     }
 
     fn consume_token(&mut self) -> Option<char> {
-        if self.current_pos >= self.input.len() { return None; }
-        let c = self.current_char();
-        self.current_pos += 1;
+        let c = self.current_char()?;
+        self.current_pos += c.len_utf8();
 
-        return c;
+        return Some(c);
     }
 
-    fn is_number_start(c: &char) -> bool { return c.is_digit(10); }
+    fn is_number_start(c: &char) -> bool { return c.is_ascii_digit(); }
 
     fn is_identifier_start(c: &char) -> bool { return c.is_alphabetic(); }
 
     fn is_whitespace(c: &char) -> bool { return c.is_whitespace(); }
 
-    fn current_char(&self) -> Option<char> { return self.input.chars().nth(self.current_pos); }
-}
\ No newline at end of file
+    fn current_char(&self) -> Option<char> { return self.peek_char(0); }
+
+    fn peek_char(&self, offset: usize) -> Option<char> { return self.input.get(self.current_pos..)?.chars().nth(offset); }
+}
+
+//Piece of a string literal, the span of an interpolation covers the expression between \( and )
+#[derive(Debug, PartialEq, Clone)]
+pub enum StringPart {
+    TEXT(String),
+    INTERPOLATION(SourceCodeSpan),
+}
+
+//Parts of a string literal token, along with the span of every escape sequence that does not stand for a character,
+//an interpolation that is never closed belongs to a string that is not terminated either, so it is left out
+pub fn decode_string(token: &Token) -> (Vec<StringPart>, Vec<SourceCodeSpan>) {
+    let literal = &token.span.literal;
+    let content = match token.kind {
+        TokenType::STRING => &literal[1..literal.len() - 1],
+        _ => &literal[1..],
+    };
+    let content_start = token.span.start + 1;
+    let mut parts = Vec::new();
+    let mut text = String::new();
+    let mut invalid_escapes = Vec::new();
+    let mut chars = content.char_indices().peekable();
+    while let Some((index, c)) = chars.next() {
+        if c != '\\' {
+            text.push(c);
+            continue;
+        }
+        let decoded = match chars.next() {
+            Some((_, 'n')) => Some('\n'),
+            Some((_, 't')) => Some('\t'),
+            Some((_, 'r')) => Some('\r'),
+            Some((_, '0')) => Some('\0'),
+            Some((_, '"')) => Some('"'),
+            Some((_, '\\')) => Some('\\'),
+            Some((_, '(')) => {
+                let expression_start = index + 2;
+                let (length, closed) = Lexer::scan_interpolation(&content[expression_start..]);
+                while chars.next_if(|(next, _)| *next < expression_start + length).is_some() {}
+                if !text.is_empty() {
+                    parts.push(StringPart::TEXT(std::mem::take(&mut text)));
+                }
+                if closed {
+                    let expression_end = expression_start + length - 1;
+                    let span = SourceCodeSpan::new(content_start + expression_start, content_start + expression_end, content[expression_start..expression_end].to_string());
+                    parts.push(StringPart::INTERPOLATION(span));
+                }
+                continue;
+            }
+            //The code point is written in hexadecimal between braces, like \u{1F600}
+            Some((_, 'u')) if chars.next_if(|(_, c)| *c == '{').is_some() => {
+                let mut digits = String::new();
+                while let Some((_, digit)) = chars.next_if(|(_, c)| c.is_ascii_alphanumeric()) {
+                    digits.push(digit);
+                }
+                let closed = chars.next_if(|(_, c)| *c == '}').is_some();
+                let code_point = u32::from_str_radix(&digits, 16).ok().filter(|_| closed && digits.len() <= 6);
+                code_point.and_then(char::from_u32)
+            }
+            _ => None,
+        };
+        match decoded {
+            Some(decoded) => text.push(decoded),
+            None => {
+                let end = chars.peek().map(|(end, _)| *end).unwrap_or(content.len());
+                let span = SourceCodeSpan::new(content_start + index, content_start + end, content[index..end].to_string());
+                invalid_escapes.push(span);
+            }
+        }
+    }
+    if !text.is_empty() {
+        parts.push(StringPart::TEXT(text));
+    }
+    return (parts, invalid_escapes);
+}
+
+#[cfg(test)]
+mod test {
+    use crate::syntax_tree::lexer::{Lexer, SourceCodeSpan, StringPart, Token, TokenType, decode_string};
+
+    fn tokens(input: &str) -> Vec<(TokenType, String)> {
+        let mut lexer = Lexer::new(input);
+        let mut tokens = Vec::new();
+        while let Some(token) = lexer.next_token() {
+            if token.kind != TokenType::WHITESPACE && token.kind != TokenType::EOF {
+                tokens.push((token.kind, token.span.literal));
+            }
+        }
+        return tokens;
+    }
+
+    #[test]
+    fn should_lex_comments_as_trivia() {
+        assert_eq!(tokens("a / b // c\n/* d /* e */ f */ g"), vec![
+            (TokenType::IDENTIFIER, "a".to_string()),
+            (TokenType::SLASH, "/".to_string()),
+            (TokenType::IDENTIFIER, "b".to_string()),
+            (TokenType::COMMENT, "// c".to_string()),
+            (TokenType::COMMENT, "/* d /* e */ f */".to_string()),
+            (TokenType::IDENTIFIER, "g".to_string()),
+        ]);
+        assert_eq!(tokens("a /* b /* c */"), vec![
+            (TokenType::IDENTIFIER, "a".to_string()),
+            (TokenType::UNTERMINATEDCOMMENT, "/* b /* c */".to_string()),
+        ]);
+    }
+
+    #[test]
+    fn should_lex_assignment_operators_and_var() {
+        let kinds: Vec<TokenType> = tokens("var x += -= *= **= /= &= |= ^= = == ** && ||").into_iter().map(|(kind, _)| kind).collect();
+        assert_eq!(kinds, vec![
+            TokenType::VAR, TokenType::IDENTIFIER, TokenType::PLUSEQUALS, TokenType::MINUSEQUALS, TokenType::ASTERISKEQUALS,
+            TokenType::POWEREQUALS, TokenType::SLASHEQUALS, TokenType::AMPERSANDEQUALS, TokenType::PIPEEQUALS, TokenType::CARETEQUALS,
+            TokenType::EQUALS, TokenType::EQUALSEQUALS, TokenType::POWER, TokenType::DOUBLEAMPERSAND, TokenType::DOUBLEPIPE,
+        ]);
+    }
+
+    #[test]
+    fn should_lex_modulo_and_shift_operators() {
+        let kinds: Vec<TokenType> = tokens("% %= << <<= >> >>= < <= > >=").into_iter().map(|(kind, _)| kind).collect();
+        assert_eq!(kinds, vec![
+            TokenType::PERCENT, TokenType::PERCENTEQUALS, TokenType::SHIFTLEFT, TokenType::SHIFTLEFTEQUALS,
+            TokenType::SHIFTRIGHT, TokenType::SHIFTRIGHTEQUALS, TokenType::LESSTHAN, TokenType::LESSTHANEQUALS,
+            TokenType::GREATERTHAN, TokenType::GREATERTHANEQUALS,
+        ]);
+    }
+
+    #[test]
+    fn should_lex_and_decode_strings() {
+        let input = "\"tab\\t \\\"quoted\\\" \\u{e9}\" \"ünïcode\" x \"open \\q\nnext";
+        assert_eq!(tokens(input), vec![
+            (TokenType::STRING, "\"tab\\t \\\"quoted\\\" \\u{e9}\"".to_string()),
+            (TokenType::STRING, "\"ünïcode\"".to_string()),
+            (TokenType::IDENTIFIER, "x".to_string()),
+            (TokenType::UNTERMINATEDSTRING, "\"open \\q".to_string()),
+            (TokenType::IDENTIFIER, "next".to_string()),
+        ]);
+        let mut lexer = Lexer::new(input);
+        let strings: Vec<Token> = std::iter::from_fn(|| lexer.next_token())
+            .filter(|token| matches!(token.kind, TokenType::STRING | TokenType::UNTERMINATEDSTRING))
+            .collect();
+        assert_eq!(&input[strings[1].span.start..strings[1].span.end], "\"ünïcode\"");
+        assert_eq!(decode_string(&strings[0]), (vec![StringPart::TEXT("tab\t \"quoted\" é".to_string())], vec![]));
+        let (parts, invalid_escapes) = decode_string(&strings[2]);
+        assert_eq!(parts, vec![StringPart::TEXT("open ".to_string())]);
+        assert_eq!(invalid_escapes.iter().map(|span| (span.start, span.literal.as_str())).collect::<Vec<_>>(), vec![(strings[2].span.start + 6, "\\q")]);
+    }
+
+    #[test]
+    fn should_lex_interpolations_as_part_of_the_string() {
+        let input = "\"a\\(f(\")\", (b))) c\" d \"\\(x\nnext";
+        assert_eq!(tokens(input), vec![
+            (TokenType::STRING, "\"a\\(f(\")\", (b))) c\"".to_string()),
+            (TokenType::IDENTIFIER, "d".to_string()),
+            (TokenType::UNTERMINATEDSTRING, "\"\\(x".to_string()),
+            (TokenType::IDENTIFIER, "next".to_string()),
+        ]);
+        let mut lexer = Lexer::new(input);
+        let string = lexer.next_token().unwrap();
+        assert_eq!(decode_string(&string), (vec![
+            StringPart::TEXT("a".to_string()),
+            StringPart::INTERPOLATION(SourceCodeSpan::new(4, 15, "f(\")\", (b))".to_string())),
+            StringPart::TEXT(" c".to_string()),
+        ], vec![]));
+    }
+
+    #[test]
+    fn should_lex_numeric_literal_forms() {
+        let kinds: Vec<TokenType> = tokens("42 1_000_000 0xFF 0b1010_0101 0o17 2.75 1e-9 2.5E+3 9223372036854775807 9223372036854775808 0x 0b102 0o8")
+            .into_iter().map(|(kind, _)| kind).collect();
+        assert_eq!(kinds, vec![
+            TokenType::NUMERAL(42), TokenType::NUMERAL(1_000_000), TokenType::NUMERAL(255), TokenType::NUMERAL(0b1010_0101), TokenType::NUMERAL(15),
+            TokenType::DOUBLE(2.75), TokenType::DOUBLE(1e-9), TokenType::DOUBLE(2500.0), TokenType::NUMERAL(i64::MAX), TokenType::OVERFLOWINGNUMERAL,
+            TokenType::INVALIDNUMERAL, TokenType::INVALIDNUMERAL, TokenType::INVALIDNUMERAL,
+        ]);
+        assert_eq!(tokens("1e308 1e309 1.5e-400")[1].0, TokenType::OVERFLOWINGDOUBLE);
+        assert_eq!(Lexer::negated_integer("9_223_372_036_854_775_808"), Some(i64::MIN));
+        assert_eq!(Lexer::negated_integer("0x8000_0000_0000_0000"), Some(i64::MIN));
+        assert_eq!(Lexer::negated_integer("9223372036854775809"), None);
+        //A dot or an "e" without digits after it does not belong to the number
+        assert_eq!(tokens("1.x 2e"), vec![
+            (TokenType::NUMERAL(1), "1".to_string()),
+            (TokenType::INVALID, ".".to_string()),
+            (TokenType::IDENTIFIER, "x".to_string()),
+            (TokenType::NUMERAL(2), "2".to_string()),
+            (TokenType::IDENTIFIER, "e".to_string()),
+        ]);
+    }
+}
@@ -1,31 +1,48 @@
+use std::rc::Rc;
+
 use termion::color;
 
-use crate::syntax_tree::lexer::{SourceCodeSpan, Token};
+use crate::syntax_tree::lexer::{SourceCodeSpan, Token, TokenType};
 
 pub mod lexer;
 pub mod parser;
 pub mod evaluator;
+pub mod scopes;
+pub mod value;
+pub mod type_checker;
+pub mod constant_folder;
+pub mod formatter;
 
 pub struct AbstractSyntaxTree {
     pub statements: Vec<ASTStatement>,
+    //Comments after the last statement
+    pub trailing_comments: Vec<Token>,
 }
 
 impl AbstractSyntaxTree {
     pub fn new() -> Self {
-        Self { statements: Vec::new() }
+        Self { statements: Vec::new(), trailing_comments: Vec::new() }
     }
 
     pub fn add_statement(&mut self, statement: ASTStatement) {
         self.statements.push(statement);
     }
 
+    //Functions can only be declared at the top level, so this lists every function in the program
+    pub fn functions(&self) -> impl Iterator<Item = &Rc<ASTFunctionDeclaration>> {
+        return self.statements.iter().filter_map(|statement| match &statement.kind {
+            ASTStatementType::FUNCTION(function) => Some(function),
+            _ => None,
+        });
+    }
+
     pub fn visit(&self, visitor: &mut dyn ASTTraverser) {
         for statement in &self.statements {
             visitor.goto_statement(statement);
         }
     }
 
-    pub fn visualize(&self) -> () {
+    pub fn visualize(&self) {
         let mut printer = ASTPrinter::new();
         self.visit(&mut printer);
         println!("{}", printer.result);
@@ -41,11 +58,65 @@ pub trait ASTTraverser {
             ASTStatementType::LETSTATEMENT(expr) => {
                 self.goto_let_statement(expr);
             }
+            ASTStatementType::BLOCK(stmt) => {
+                self.goto_block_statement(stmt);
+            }
+            ASTStatementType::IF(stmt) => {
+                self.goto_if_statement(stmt);
+            }
+            ASTStatementType::WHILE(stmt) => {
+                self.goto_while_statement(stmt);
+            }
+            ASTStatementType::BREAK(keyword) => {
+                self.goto_break_statement(keyword);
+            }
+            ASTStatementType::CONTINUE(keyword) => {
+                self.goto_continue_statement(keyword);
+            }
+            ASTStatementType::FUNCTION(function) => {
+                self.goto_function_declaration(function);
+            }
+            ASTStatementType::RETURN(stmt) => {
+                self.goto_return_statement(stmt);
+            }
         }
     }
 
     fn goto_let_statement(&mut self, let_statement: &ASTLetStatement);
 
+    fn goto_block_statement(&mut self, block_statement: &ASTBlockStatement) {
+        for statement in &block_statement.statements {
+            self.goto_statement(statement);
+        }
+    }
+
+    fn goto_if_statement(&mut self, if_statement: &ASTIfStatement) {
+        self.goto_expression(&if_statement.condition);
+        self.goto_statement(&if_statement.then_branch);
+        if let Some(else_branch) = &if_statement.else_branch {
+            self.goto_statement(&else_branch.else_statement);
+        }
+    }
+
+    fn goto_while_statement(&mut self, while_statement: &ASTWhileStatement) {
+        self.goto_expression(&while_statement.condition);
+        self.goto_statement(&while_statement.body);
+    }
+
+    fn goto_break_statement(&mut self, break_keyword: &Token) { }
+
+    fn goto_continue_statement(&mut self, continue_keyword: &Token) { }
+
+    fn goto_function_declaration(&mut self, function_declaration: &ASTFunctionDeclaration) {
+        self.goto_statement(&function_declaration.body);
+    }
+
+    fn goto_return_statement(&mut self, return_statement: &ASTReturnStatement) {
+        if let Some(return_value) = &return_statement.return_value {
+            self.goto_expression(return_value);
+        }
+    }
+
     fn goto_statement(&mut self, statement: &ASTStatement) {
         self.do_visit_statement(statement);
     }
@@ -53,11 +124,17 @@ pub trait ASTTraverser {
     fn expression_dispatch(&mut self, expression: &Expression) {
         match &expression.kind {
             ExpressionType::NUMBER(number) => self.goto_number_expression(number),
+            ExpressionType::DOUBLE(double) => self.goto_double_expression(double),
+            ExpressionType::BOOLEAN(boolean) => self.goto_boolean_expression(boolean),
+            ExpressionType::STRING(string) => self.goto_string_expression(string),
+            ExpressionType::INTERPOLATION(interpolation) => self.goto_interpolation_expression(interpolation),
             ExpressionType::BINARY(expr) => self.goto_binary_expression(expr),
             ExpressionType::PARENTHESIZED(expr) => self.goto_parenthesized_expression(expr),
             ExpressionType::ERROR(span) => self.goto_error(span),
             ExpressionType::VARIABLE(expr) => self.goto_variable_expression(expr),
             ExpressionType::UNARY(expr) => self.goto_unary_expression(expr),
+            ExpressionType::CALL(expr) => self.goto_call_expression(expr),
+            ExpressionType::ASSIGNMENT(expr) => self.goto_assignment_expression(expr),
         }
     }
 
@@ -69,6 +146,20 @@ pub trait ASTTraverser {
 
     fn goto_number_expression(&mut self, number: &NumberExpression);
 
+    fn goto_double_expression(&mut self, double: &DoubleExpression) { }
+
+    fn goto_boolean_expression(&mut self, boolean: &BooleanExpression) { }
+
+    fn goto_string_expression(&mut self, string: &StringExpression) { }
+
+    fn goto_interpolation_expression(&mut self, interpolation: &InterpolationExpression) {
+        for part in &interpolation.parts {
+            if let InterpolationPart::EXPRESSION(expression, _) = part {
+                self.goto_expression(expression);
+            }
+        }
+    }
+
     fn goto_error(&mut self, span: &SourceCodeSpan);
 
     fn goto_unary_expression(&mut self, unary_expression: &UnaryExpression);
@@ -82,6 +173,16 @@ pub trait ASTTraverser {
         self.goto_expression(&parenthesized_expression.expression);
     }
 
+    fn goto_call_expression(&mut self, call_expression: &CallExpression) {
+        for argument in &call_expression.arguments {
+            self.goto_expression(argument);
+        }
+    }
+
+    fn goto_assignment_expression(&mut self, assignment_expression: &AssignmentExpression) {
+        self.goto_expression(&assignment_expression.value);
+    }
+
 }
 
 pub struct ASTPrinter {
@@ -94,10 +195,14 @@ impl ASTPrinter {
     const TEXT_COLOR: color::LightWhite = color::LightWhite;
     const KEYWORD_COLOR: color::Blue = color::Blue;
     const VARIABLE_COLOR: color::LightBlue = color::LightBlue;
+    const STRING_COLOR: color::LightYellow = color::LightYellow;
 
-    fn add_space(&mut self) { self.result.push_str(" "); }
+    fn add_space(&mut self) { self.result.push(' '); }
+
+    fn add_newline(&mut self) { self.result.push('\n'); }
+
+    fn add_indent(&mut self) { self.result.push_str(&"    ".repeat(self.indent)); }
 
-    fn add_newline(&mut self) { self.result.push_str("\n"); }
 
     pub fn new() -> Self {
         Self { indent: 0, result: String::new() }
@@ -106,16 +211,93 @@ impl ASTPrinter {
 
 impl ASTTraverser for ASTPrinter {
     fn goto_let_statement(&mut self, let_statement: &ASTLetStatement) {
-        self.result.push_str(&format!("{}let", Self::KEYWORD_COLOR.fg_str()));
+        self.result.push_str(&format!("{}{}", Self::KEYWORD_COLOR.fg_str(), let_statement.keyword.span.literal));
         self.add_space();
         self.result.push_str(&format!("{}{}", Self::TEXT_COLOR.fg_str(), let_statement.identifier.span.literal, ));
+        if let Some(type_annotation) = &let_statement.type_annotation {
+            self.result.push_str(&format!(": {}{}", Self::KEYWORD_COLOR.fg_str(), type_annotation.span.literal));
+        }
         self.add_space();
         self.result.push_str(&format!("{}=", Self::TEXT_COLOR.fg_str(), ));
         self.add_space();
         self.goto_expression(&let_statement.initializer);
     }
 
+    fn goto_block_statement(&mut self, block_statement: &ASTBlockStatement) {
+        self.result.push_str(&format!("{}{{", Self::TEXT_COLOR.fg_str()));
+        self.add_newline();
+        self.indent += 1;
+        for statement in &block_statement.statements {
+            self.goto_statement(statement);
+        }
+        self.indent -= 1;
+        self.add_indent();
+        self.result.push_str(&format!("{}}}", Self::TEXT_COLOR.fg_str()));
+    }
+
+    fn goto_if_statement(&mut self, if_statement: &ASTIfStatement) {
+        self.result.push_str(&format!("{}if", Self::KEYWORD_COLOR.fg_str()));
+        self.add_space();
+        self.goto_expression(&if_statement.condition);
+        self.add_space();
+        //Nested statements are printed inline, goto_statement would start a new line
+        self.do_visit_statement(&if_statement.then_branch);
+        if let Some(else_branch) = &if_statement.else_branch {
+            self.add_space();
+            self.result.push_str(&format!("{}else", Self::KEYWORD_COLOR.fg_str()));
+            self.add_space();
+            self.do_visit_statement(&else_branch.else_statement);
+        }
+    }
+
+    fn goto_while_statement(&mut self, while_statement: &ASTWhileStatement) {
+        self.result.push_str(&format!("{}while", Self::KEYWORD_COLOR.fg_str()));
+        self.add_space();
+        self.goto_expression(&while_statement.condition);
+        self.add_space();
+        self.do_visit_statement(&while_statement.body);
+    }
+
+    fn goto_break_statement(&mut self, break_keyword: &Token) {
+        self.result.push_str(&format!("{}break", Self::KEYWORD_COLOR.fg_str()));
+    }
+
+    fn goto_continue_statement(&mut self, continue_keyword: &Token) {
+        self.result.push_str(&format!("{}continue", Self::KEYWORD_COLOR.fg_str()));
+    }
+
+    fn goto_function_declaration(&mut self, function_declaration: &ASTFunctionDeclaration) {
+        self.result.push_str(&format!("{}func", Self::KEYWORD_COLOR.fg_str()));
+        self.add_space();
+        self.result.push_str(&format!("{}{}(", Self::TEXT_COLOR.fg_str(), function_declaration.identifier.span.literal));
+        for (index, parameter) in function_declaration.parameters.iter().enumerate() {
+            if index > 0 {
+                self.result.push_str(", ");
+            }
+            self.result.push_str(&format!("{}{}{}", Self::VARIABLE_COLOR.fg_str(), parameter.identifier.span.literal, Self::TEXT_COLOR.fg_str()));
+            if let Some(type_annotation) = &parameter.type_annotation {
+                self.result.push_str(&format!(": {}{}{}", Self::KEYWORD_COLOR.fg_str(), type_annotation.span.literal, Self::TEXT_COLOR.fg_str()));
+            }
+        }
+        self.result.push(')');
+        self.add_space();
+        if let Some(return_type) = &function_declaration.return_type {
+            self.result.push_str(&format!("-> {}{}{}", Self::KEYWORD_COLOR.fg_str(), return_type.span.literal, Self::TEXT_COLOR.fg_str()));
+            self.add_space();
+        }
+        self.do_visit_statement(&function_declaration.body);
+    }
+
+    fn goto_return_statement(&mut self, return_statement: &ASTReturnStatement) {
+        self.result.push_str(&format!("{}return", Self::KEYWORD_COLOR.fg_str()));
+        if let Some(return_value) = &return_statement.return_value {
+            self.add_space();
+            self.goto_expression(return_value);
+        }
+    }
+
     fn goto_statement(&mut self, statement: &ASTStatement) {
+        self.add_indent();
         Self::do_visit_statement(self, statement);
         self.result.push_str(&format!("{}\n", color::Fg(color::Reset) ));
     }
@@ -125,7 +307,34 @@ impl ASTTraverser for ASTPrinter {
     }
 
     fn goto_number_expression(&mut self, number: &NumberExpression) {
-        self.result.push_str(&format!("{}{}", Self::NUMBER_COLOR.fg_str(), number.number ));
+        self.result.push_str(&format!("{}{}", Self::NUMBER_COLOR.fg_str(), number.literal ));
+    }
+
+    fn goto_double_expression(&mut self, double: &DoubleExpression) {
+        self.result.push_str(&format!("{}{}", Self::NUMBER_COLOR.fg_str(), double.literal ));
+    }
+
+    fn goto_boolean_expression(&mut self, boolean: &BooleanExpression) {
+        self.result.push_str(&format!("{}{}", Self::KEYWORD_COLOR.fg_str(), boolean.value ));
+    }
+
+    fn goto_string_expression(&mut self, string: &StringExpression) {
+        self.result.push_str(&format!("{}{}", Self::STRING_COLOR.fg_str(), string.literal() ));
+    }
+
+    fn goto_interpolation_expression(&mut self, interpolation: &InterpolationExpression) {
+        self.result.push_str(&format!("{}\"", Self::STRING_COLOR.fg_str()));
+        for part in &interpolation.parts {
+            match part {
+                InterpolationPart::TEXT(text) => self.result.push_str(&format!("{}{}", Self::STRING_COLOR.fg_str(), escape_string(text))),
+                InterpolationPart::EXPRESSION(expression, _) => {
+                    self.result.push_str(&format!("{}\\(", Self::TEXT_COLOR.fg_str()));
+                    self.goto_expression(expression);
+                    self.result.push_str(&format!("{})", Self::TEXT_COLOR.fg_str()));
+                }
+            }
+        }
+        self.result.push_str(&format!("{}\"", Self::STRING_COLOR.fg_str()));
     }
 
     fn goto_error(&mut self, span: &SourceCodeSpan) {
@@ -150,51 +359,183 @@ impl ASTTraverser for ASTPrinter {
         self.goto_expression(&parenthesized_expression.expression);
         self.result.push_str(&format!("{}{}", Self::TEXT_COLOR.fg_str(), ")", ));
     }
+
+    fn goto_call_expression(&mut self, call_expression: &CallExpression) {
+        self.result.push_str(&format!("{}{}(", Self::TEXT_COLOR.fg_str(), call_expression.identifier()));
+        for (index, argument) in call_expression.arguments.iter().enumerate() {
+            if index > 0 {
+                self.result.push_str(&format!("{}, ", Self::TEXT_COLOR.fg_str()));
+            }
+            self.goto_expression(argument);
+        }
+        self.result.push_str(&format!("{})", Self::TEXT_COLOR.fg_str()));
+    }
+
+    fn goto_assignment_expression(&mut self, assignment_expression: &AssignmentExpression) {
+        self.result.push_str(&format!("{}{}", Self::VARIABLE_COLOR.fg_str(), assignment_expression.identifier()));
+        self.add_space();
+        self.result.push_str(&format!("{}{}", Self::TEXT_COLOR.fg_str(), assignment_expression.operator_token.span.literal));
+        self.add_space();
+        self.goto_expression(&assignment_expression.value);
+    }
 }
 
 
 pub enum ASTStatementType {
     EXPRESSION(Expression),
     LETSTATEMENT(ASTLetStatement),
+    BLOCK(ASTBlockStatement),
+    IF(ASTIfStatement),
+    WHILE(ASTWhileStatement),
+    BREAK(Token),
+    CONTINUE(Token),
+    FUNCTION(Rc<ASTFunctionDeclaration>),
+    RETURN(ASTReturnStatement),
 }
 
 pub struct ASTLetStatement {
+    pub keyword: Token,
     pub identifier: Token,
+    pub type_annotation: Option<Token>,
     pub initializer: Expression,
 }
 
+impl ASTLetStatement {
+    //Only variables declared with "var" can be assigned to after their declaration
+    pub fn is_mutable(&self) -> bool { return self.keyword.kind == TokenType::VAR; }
+}
+
+pub struct ASTBlockStatement {
+    pub statements: Vec<ASTStatement>,
+    //Comments before the closing brace
+    pub closing_comments: Vec<Token>,
+}
+
+pub struct ASTIfStatement {
+    pub if_keyword: Token,
+    pub condition: Expression,
+    pub then_branch: Box<ASTStatement>,
+    pub else_branch: Option<ASTElseStatement>,
+}
+
+pub struct ASTWhileStatement {
+    pub while_keyword: Token,
+    pub condition: Expression,
+    pub body: Box<ASTStatement>,
+}
+
+pub struct ASTParameter {
+    pub identifier: Token,
+    pub type_annotation: Option<Token>,
+}
+
+impl ASTParameter {
+    pub fn new(identifier: Token, type_annotation: Option<Token>) -> Self {
+        ASTParameter { identifier, type_annotation }
+    }
+}
+
+pub struct ASTFunctionDeclaration {
+    pub func_keyword: Token,
+    pub identifier: Token,
+    pub parameters: Vec<ASTParameter>,
+    pub return_type: Option<Token>,
+    pub body: Box<ASTStatement>,
+}
+
+impl ASTFunctionDeclaration {
+    pub fn identifier(&self) -> &str { return &self.identifier.span.literal; }
+}
+
+pub struct ASTReturnStatement {
+    pub return_keyword: Token,
+    pub return_value: Option<Expression>,
+}
+
+//Holds either a block or, for "else if" chains, another if statement
+pub struct ASTElseStatement {
+    pub else_keyword: Token,
+    pub else_statement: Box<ASTStatement>,
+}
+
+impl ASTElseStatement {
+    pub fn new(else_keyword: Token, else_statement: ASTStatement) -> Self {
+        ASTElseStatement { else_keyword, else_statement: Box::new(else_statement) }
+    }
+}
+
 pub struct ASTStatement {
-    kind: ASTStatementType,
+    pub kind: ASTStatementType,
+    //Comments before the statement, along with the ones inside it that no nested statement took
+    pub leading_comments: Vec<Token>,
+    //Comments after the last token of the statement on the same line
+    pub trailing_comments: Vec<Token>,
 }
 
 impl ASTStatement {
-    pub fn new(kind: ASTStatementType) -> Self { ASTStatement { kind } }
+    pub fn new(kind: ASTStatementType) -> Self { ASTStatement { kind, leading_comments: Vec::new(), trailing_comments: Vec::new() } }
 
     pub fn expression(expr: Expression) -> Self {
         return ASTStatement::new(ASTStatementType::EXPRESSION(expr));
     }
 
-    pub fn let_statement(identifier: Token, initializer: Expression) -> Self {
-        return ASTStatement::new(ASTStatementType::LETSTATEMENT(ASTLetStatement { identifier, initializer }));
+    pub fn let_statement(keyword: Token, identifier: Token, type_annotation: Option<Token>, initializer: Expression) -> Self {
+        return ASTStatement::new(ASTStatementType::LETSTATEMENT(ASTLetStatement { keyword, identifier, type_annotation, initializer }));
+    }
+
+    pub fn block_statement(statements: Vec<ASTStatement>, closing_comments: Vec<Token>) -> Self {
+        return ASTStatement::new(ASTStatementType::BLOCK(ASTBlockStatement { statements, closing_comments }));
+    }
+
+    pub fn if_statement(if_keyword: Token, condition: Expression, then_branch: ASTStatement, else_branch: Option<ASTElseStatement>) -> Self {
+        return ASTStatement::new(ASTStatementType::IF(ASTIfStatement { if_keyword, condition, then_branch: Box::new(then_branch), else_branch }));
+    }
+
+    pub fn while_statement(while_keyword: Token, condition: Expression, body: ASTStatement) -> Self {
+        return ASTStatement::new(ASTStatementType::WHILE(ASTWhileStatement { while_keyword, condition, body: Box::new(body) }));
+    }
+
+    pub fn break_statement(break_keyword: Token) -> Self {
+        return ASTStatement::new(ASTStatementType::BREAK(break_keyword));
+    }
+
+    pub fn continue_statement(continue_keyword: Token) -> Self {
+        return ASTStatement::new(ASTStatementType::CONTINUE(continue_keyword));
+    }
+
+    pub fn function_declaration(func_keyword: Token, identifier: Token, parameters: Vec<ASTParameter>, return_type: Option<Token>, body: ASTStatement) -> Self {
+        return ASTStatement::new(ASTStatementType::FUNCTION(Rc::new(ASTFunctionDeclaration { func_keyword, identifier, parameters, return_type, body: Box::new(body) })));
+    }
+
+    pub fn return_statement(return_keyword: Token, return_value: Option<Expression>) -> Self {
+        return ASTStatement::new(ASTStatementType::RETURN(ASTReturnStatement { return_keyword, return_value }));
     }
 }
 
 pub enum ExpressionType {
     NUMBER( NumberExpression ),
+    DOUBLE( DoubleExpression ),
+    BOOLEAN( BooleanExpression ),
+    STRING( StringExpression ),
+    INTERPOLATION( InterpolationExpression ),
     BINARY( BinaryExpression ),
     UNARY( UnaryExpression ),
     PARENTHESIZED( ParenthesizedExpression ),
     VARIABLE( VariableExpression ),
+    CALL( CallExpression ),
+    ASSIGNMENT( AssignmentExpression ),
     ERROR( SourceCodeSpan ),
 }
 
+#[derive(Debug, Clone, Copy, PartialEq)]
 pub enum UnaryOperatorType {
     MINUS,
     NOT,
+    LOGICALNOT,
 }
 pub struct UnaryOperator {
-    kind: UnaryOperatorType,
-    token: Token,
+    pub kind: UnaryOperatorType,
+    pub token: Token,
 }
 impl UnaryOperator {
     pub fn new(kind: UnaryOperatorType, token: Token) -> Self {
@@ -215,59 +556,173 @@ impl VariableExpression {
     pub fn identifier(&self) -> &str { return &self.identifier.span.literal; }
 }
 
-#[derive(Debug)]
+pub struct CallExpression {
+    pub identifier: Token,
+    pub arguments: Vec<Expression>,
+}
+
+impl CallExpression {
+    pub fn identifier(&self) -> &str { return &self.identifier.span.literal; }
+}
+
+pub struct AssignmentExpression {
+    pub identifier: Token,
+    pub operator_token: Token,
+    //The operator of a compound assignment like "+=", None for a plain "="
+    pub operator: Option<BinaryOperatorType>,
+    pub value: Box<Expression>,
+}
+
+impl AssignmentExpression {
+    pub fn identifier(&self) -> &str { return &self.identifier.span.literal; }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq)]
 pub enum BinaryOperatorType {
     PLUS,
     MINUS,
     MULTIPLY,
     DIVIDE,
+    MODULO,
     POWER,
+    SHIFTLEFT,
+    SHIFTRIGHT,
     AND,
     OR,
     XOR,
+    EQUALS,
+    NOTEQUALS,
+    LESSTHAN,
+    LESSTHANOREQUAL,
+    GREATERTHAN,
+    GREATERTHANOREQUAL,
+    LOGICALAND,
+    LOGICALOR,
 }
 
-pub struct BinaryOperator {
-    kind: BinaryOperatorType,
-    token: Token,
-}
-
-impl BinaryOperator {
-    pub fn new(kind: BinaryOperatorType, token: Token) -> Self {
-        BinaryOperator { kind, token }
-    }
-
+impl BinaryOperatorType {
     pub fn precedence(&self) -> u8 {
-        match self.kind {
+        match self {
             //Wikipedia based precedence table
             BinaryOperatorType::POWER => 13,
             BinaryOperatorType::MULTIPLY => 12,
             BinaryOperatorType::DIVIDE => 12,
+            BinaryOperatorType::MODULO => 12,
             BinaryOperatorType::PLUS => 11,
             BinaryOperatorType::MINUS => 11,
+            BinaryOperatorType::SHIFTLEFT => 10,
+            BinaryOperatorType::SHIFTRIGHT => 10,
+            BinaryOperatorType::LESSTHAN => 9,
+            BinaryOperatorType::LESSTHANOREQUAL => 9,
+            BinaryOperatorType::GREATERTHAN => 9,
+            BinaryOperatorType::GREATERTHANOREQUAL => 9,
+            BinaryOperatorType::EQUALS => 8,
+            BinaryOperatorType::NOTEQUALS => 8,
             BinaryOperatorType::AND => 7,
             BinaryOperatorType::XOR => 6,
             BinaryOperatorType::OR => 5,
+            BinaryOperatorType::LOGICALAND => 4,
+            BinaryOperatorType::LOGICALOR => 3,
+        }
+    }
+}
+
+//Side that operators of the same precedence group to, "a - b - c" is "(a - b) - c" while "a ** b ** c" is "a ** (b ** c)"
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum Associativity {
+    LEFT,
+    RIGHT,
+}
+
+pub struct BinaryOperator {
+    pub kind: BinaryOperatorType,
+    pub token: Token,
+}
+
+impl BinaryOperator {
+    pub fn new(kind: BinaryOperatorType, token: Token) -> Self {
+        BinaryOperator { kind, token }
+    }
+
+    pub fn precedence(&self) -> u8 {
+        return self.kind.precedence();
+    }
+
+    pub fn associativity(&self) -> Associativity {
+        match self.kind {
+            BinaryOperatorType::POWER => Associativity::RIGHT,
+            _ => Associativity::LEFT,
         }
     }
 }
 
 pub struct BinaryExpression {
-    left: Box<Expression>,
-    operator: BinaryOperator,
-    right: Box<Expression>,
+    pub left: Box<Expression>,
+    pub operator: BinaryOperator,
+    pub right: Box<Expression>,
 }
 
 pub struct NumberExpression {
-    number: i64,
+    pub number: i64,
+    //Digits as written in the source, like "0xFF" or "1_000"
+    pub literal: String,
+}
+
+pub struct DoubleExpression {
+    pub value: f64,
+    pub literal: String,
+}
+
+pub struct BooleanExpression {
+    pub value: bool,
+}
+
+pub struct StringExpression {
+    pub value: String,
+}
+
+impl StringExpression {
+    //Quoted literal that evaluates to the value
+    pub fn literal(&self) -> String {
+        return format!("\"{}\"", escape_string(&self.value));
+    }
+}
+
+//Text as it has to be written between quotes, quotes, backslashes and control characters are escaped
+pub fn escape_string(value: &str) -> String {
+    let mut escaped = String::new();
+    for c in value.chars() {
+        match c {
+            '"' => escaped.push_str("\\\""),
+            '\\' => escaped.push_str("\\\\"),
+            '\n' => escaped.push_str("\\n"),
+            '\t' => escaped.push_str("\\t"),
+            '\r' => escaped.push_str("\\r"),
+            '\0' => escaped.push_str("\\0"),
+            c if c.is_control() => escaped.push_str(&format!("\\u{{{:X}}}", c as u32)),
+            c => escaped.push(c),
+        }
+    }
+    return escaped;
+}
+
+pub enum InterpolationPart {
+    TEXT(String),
+    //The span covers the source of the expression between \( and )
+    EXPRESSION(Box<Expression>, SourceCodeSpan),
+}
+
+//String literal with \(expression) parts, the values of the expressions are converted to text
+pub struct InterpolationExpression {
+    pub parts: Vec<InterpolationPart>,
 }
 
 pub struct ParenthesizedExpression {
-    expression: Box<Expression>,
+    pub expression: Box<Expression>,
 }
 
 pub struct Expression {
-    kind: ExpressionType,
+    pub kind: ExpressionType,
 }
 
 impl Expression {
@@ -276,7 +731,32 @@ impl Expression {
     }
 
     pub fn number(number: i64) -> Self {
-        return Expression::new(ExpressionType::NUMBER(NumberExpression { number }));
+        return Expression::number_literal(number, number.to_string());
+    }
+
+    pub fn number_literal(number: i64, literal: String) -> Self {
+        return Expression::new(ExpressionType::NUMBER(NumberExpression { number, literal }));
+    }
+
+    //Folded constants get a literal that still reads as a double, like 2.0
+    pub fn double(value: f64) -> Self {
+        return Expression::double_literal(value, format!("{:?}", value));
+    }
+
+    pub fn double_literal(value: f64, literal: String) -> Self {
+        return Expression::new(ExpressionType::DOUBLE(DoubleExpression { value, literal }));
+    }
+
+    pub fn boolean(value: bool) -> Self {
+        return Expression::new(ExpressionType::BOOLEAN(BooleanExpression { value }));
+    }
+
+    pub fn string(value: String) -> Self {
+        return Expression::new(ExpressionType::STRING(StringExpression { value }));
+    }
+
+    pub fn interpolation(parts: Vec<InterpolationPart>) -> Self {
+        return Expression::new(ExpressionType::INTERPOLATION(InterpolationExpression { parts }));
     }
 
     pub fn binary(operator: BinaryOperator, left: Expression, right: Expression) -> Self {
@@ -291,10 +771,18 @@ impl Expression {
         return Expression::new(ExpressionType::VARIABLE(VariableExpression { identifier }));
     }
 
+    pub fn call(identifier: Token, arguments: Vec<Expression>) -> Self {
+        return Expression::new(ExpressionType::CALL(CallExpression { identifier, arguments }));
+    }
+
     pub fn unary(operator: UnaryOperator, operand: Expression) -> Self {
         return Expression::new(ExpressionType::UNARY(UnaryExpression { operator, operand: Box::new(operand) }));
     }
 
+    pub fn assignment(identifier: Token, operator_token: Token, operator: Option<BinaryOperatorType>, value: Expression) -> Self {
+        return Expression::new(ExpressionType::ASSIGNMENT(AssignmentExpression { identifier, operator_token, operator, value: Box::new(value) }));
+    }
+
     pub fn error(span: SourceCodeSpan) -> Self {
         return Expression::new(ExpressionType::ERROR(span));
     }
@@ -302,16 +790,23 @@ impl Expression {
 
 #[cfg(test)]
 mod test {
-    use crate::syntax_tree::{AbstractSyntaxTree, BinaryExpression, ASTLetStatement, NumberExpression, ParenthesizedExpression, UnaryExpression, VariableExpression, ASTTraverser, lexer::SourceCodeSpan};
+    use crate::syntax_tree::{AbstractSyntaxTree, BinaryExpression, BooleanExpression, ASTLetStatement, ASTBlockStatement, ASTIfStatement, ASTWhileStatement, NumberExpression, ParenthesizedExpression, UnaryExpression, VariableExpression, ASTTraverser, lexer::{SourceCodeSpan, Token}};
     use crate::compilation_unit::CompilationUnit;
 
     #[derive(Debug, PartialEq, Eq)]
     enum TestASTNode {
         NUMBER(i64),
+        BOOLEAN(bool),
         BINARY,
         UNARY,
         PARENTHESIZED,
         LETSTATEMENT,
+        BLOCK,
+        IF,
+        ELSE,
+        WHILE,
+        BREAK,
+        CONTINUE,
         VAR(String),
     }
 
@@ -353,6 +848,37 @@ mod test {
             self.goto_expression(&let_statement.initializer);
         }
 
+        fn goto_block_statement(&mut self, block_statement: &ASTBlockStatement) {
+            self.actual.push(TestASTNode::BLOCK);
+            for statement in &block_statement.statements {
+                self.goto_statement(statement);
+            }
+        }
+
+        fn goto_if_statement(&mut self, if_statement: &ASTIfStatement) {
+            self.actual.push(TestASTNode::IF);
+            self.goto_expression(&if_statement.condition);
+            self.goto_statement(&if_statement.then_branch);
+            if let Some(else_branch) = &if_statement.else_branch {
+                self.actual.push(TestASTNode::ELSE);
+                self.goto_statement(&else_branch.else_statement);
+            }
+        }
+
+        fn goto_while_statement(&mut self, while_statement: &ASTWhileStatement) {
+            self.actual.push(TestASTNode::WHILE);
+            self.goto_expression(&while_statement.condition);
+            self.goto_statement(&while_statement.body);
+        }
+
+        fn goto_break_statement(&mut self, break_keyword: &Token) {
+            self.actual.push(TestASTNode::BREAK);
+        }
+
+        fn goto_continue_statement(&mut self, continue_keyword: &Token) {
+            self.actual.push(TestASTNode::CONTINUE);
+        }
+
         fn goto_variable_expression(&mut self, variable_expression: &VariableExpression) {
             self.actual.push(TestASTNode::VAR( variable_expression.identifier().to_string() ));
         }
@@ -361,6 +887,10 @@ mod test {
             self.actual.push(TestASTNode::NUMBER(number.number));
         }
 
+        fn goto_boolean_expression(&mut self, boolean: &BooleanExpression) {
+            self.actual.push(TestASTNode::BOOLEAN(boolean.value));
+        }
+
         fn goto_unary_expression(&mut self, unary_expression: &UnaryExpression) {
             self.actual.push(TestASTNode::UNARY);
             self.goto_expression(&unary_expression.operand);
@@ -388,4 +918,207 @@ mod test {
         let verifier = ASTVerifier::new(input, expected);
         verifier.verify();
     }
+
+    //Writes every operation in parentheses to show how the parser grouped it
+    struct GroupingPrinter {
+        result: String,
+    }
+
+    impl ASTTraverser for GroupingPrinter {
+        fn goto_let_statement(&mut self, let_statement: &ASTLetStatement) {
+            self.goto_expression(&let_statement.initializer);
+        }
+
+        fn goto_variable_expression(&mut self, variable_expression: &VariableExpression) {
+            self.result.push_str(variable_expression.identifier());
+        }
+
+        fn goto_number_expression(&mut self, number: &NumberExpression) {
+            self.result.push_str(&number.number.to_string());
+        }
+
+        fn goto_error(&mut self, span: &SourceCodeSpan) {
+            self.result.push_str(&span.literal);
+        }
+
+        fn goto_unary_expression(&mut self, unary_expression: &UnaryExpression) {
+            self.result.push('(');
+            self.result.push_str(&unary_expression.operator.token.span.literal);
+            self.goto_expression(&unary_expression.operand);
+            self.result.push(')');
+        }
+
+        fn goto_binary_expression(&mut self, binary_expression: &BinaryExpression) {
+            self.result.push('(');
+            self.goto_expression(&binary_expression.left);
+            self.result.push_str(&format!(" {} ", binary_expression.operator.token.span.literal));
+            self.goto_expression(&binary_expression.right);
+            self.result.push(')');
+        }
+    }
+
+    fn assert_grouping(input: &str, expected: &str) {
+        let compilation_unit = CompilationUnit::parse(input);
+        assert!(!compilation_unit.diagnostics_vector.borrow().has_errors(), "Expected no errors, but got {:?}", compilation_unit.diagnostics_vector.borrow().diagnostics);
+        let mut printer = GroupingPrinter { result: String::new() };
+        compilation_unit.ast.visit(&mut printer);
+        assert_eq!(printer.result, expected, "Unexpected grouping of {}", input);
+    }
+
+    #[test]
+    fn should_parse_if_statement() {
+        let input = "let a = true; if a { let b = 2 }";
+        let expected = vec![
+            TestASTNode::LETSTATEMENT,
+            TestASTNode::BOOLEAN(true),
+            TestASTNode::IF,
+            TestASTNode::VAR("a".to_string()),
+            TestASTNode::BLOCK,
+            TestASTNode::LETSTATEMENT,
+            TestASTNode::NUMBER(2),
+        ];
+
+        assert_ast(input, expected);
+    }
+
+    #[test]
+    fn should_parse_else_if_chain() {
+        let input = "if false { 1 } else if true { 3 } else { 4 }";
+        let expected = vec![
+            TestASTNode::IF,
+            TestASTNode::BOOLEAN(false),
+            TestASTNode::BLOCK,
+            TestASTNode::NUMBER(1),
+            TestASTNode::ELSE,
+            TestASTNode::IF,
+            TestASTNode::BOOLEAN(true),
+            TestASTNode::BLOCK,
+            TestASTNode::NUMBER(3),
+            TestASTNode::ELSE,
+            TestASTNode::BLOCK,
+            TestASTNode::NUMBER(4),
+        ];
+
+        assert_ast(input, expected);
+    }
+
+    #[test]
+    fn should_parse_while_statement() {
+        let input = "while true { break; continue }";
+        let expected = vec![
+            TestASTNode::WHILE,
+            TestASTNode::BOOLEAN(true),
+            TestASTNode::BLOCK,
+            TestASTNode::BREAK,
+            TestASTNode::CONTINUE,
+        ];
+
+        assert_ast(input, expected);
+    }
+
+    #[test]
+    fn should_parse_nested_block_statements() {
+        let input = "{ let a = 1; { a } }";
+        let expected = vec![
+            TestASTNode::BLOCK,
+            TestASTNode::LETSTATEMENT,
+            TestASTNode::NUMBER(1),
+            TestASTNode::BLOCK,
+            TestASTNode::VAR("a".to_string()),
+        ];
+
+        assert_ast(input, expected);
+    }
+
+    #[test]
+    fn should_respect_comparison_and_logical_precedence() {
+        let input = "let a = 1; let b = true; a + a < a == !b || b && a != a";
+        let expected = vec![
+            TestASTNode::LETSTATEMENT,
+            TestASTNode::NUMBER(1),
+            TestASTNode::LETSTATEMENT,
+            TestASTNode::BOOLEAN(true),
+            TestASTNode::BINARY,
+            TestASTNode::BINARY,
+            TestASTNode::BINARY,
+            TestASTNode::BINARY,
+            TestASTNode::VAR("a".to_string()),
+            TestASTNode::VAR("a".to_string()),
+            TestASTNode::VAR("a".to_string()),
+            TestASTNode::UNARY,
+            TestASTNode::VAR("b".to_string()),
+            TestASTNode::BINARY,
+            TestASTNode::VAR("b".to_string()),
+            TestASTNode::BINARY,
+            TestASTNode::VAR("a".to_string()),
+            TestASTNode::VAR("a".to_string()),
+        ];
+
+        assert_ast(input, expected);
+    }
+
+    #[test]
+    fn should_group_operators_by_precedence() {
+        assert_grouping("a || b && c | d ^ e & f == g < h << i + j * k ** l", "(a || (b && (c | (d ^ (e & (f == (g < (h << (i + (j * (k ** l)))))))))))");
+        assert_grouping("a ** b * c + d << e < f == g & h ^ i | j && k || l", "(((((((((((a ** b) * c) + d) << e) < f) == g) & h) ^ i) | j) && k) || l)");
+    }
+
+    #[test]
+    fn should_group_operators_of_the_same_precedence() {
+        assert_grouping("a - b + c - d", "(((a - b) + c) - d)");
+        assert_grouping("a / b * c % d", "(((a / b) * c) % d)");
+        assert_grouping("a << b >> c", "((a << b) >> c)");
+        assert_grouping("a < b >= c", "((a < b) >= c)");
+        assert_grouping("a == b != c", "((a == b) != c)");
+        assert_grouping("a & b & c", "((a & b) & c)");
+        assert_grouping("a ^ b ^ c", "((a ^ b) ^ c)");
+        assert_grouping("a | b | c", "((a | b) | c)");
+        assert_grouping("a && b && c", "((a && b) && c)");
+        assert_grouping("a || b || c", "((a || b) || c)");
+        assert_grouping("a ** b ** c", "(a ** (b ** c))");
+    }
+
+    #[test]
+    fn should_bind_prefix_operators_looser_than_power() {
+        assert_grouping("-a ** b", "(-(a ** b))");
+        assert_grouping("-a * b", "((-a) * b)");
+        assert_grouping("~a ** b ** c + d", "((~(a ** (b ** c))) + d)");
+        assert_grouping("a ** -b ** c", "(a ** (-(b ** c)))");
+        assert_grouping("(-a) ** b", "((-a) ** b)");
+        assert_grouping("!a && -b", "((!a) && (-b))");
+    }
+
+    #[test]
+    fn should_fold_constant_subexpressions() {
+        let input = "let a = (2 + 3) * 4; let b = a * (10 - -2) > 7 || false";
+        let expected = vec![
+            TestASTNode::LETSTATEMENT,
+            TestASTNode::NUMBER(20),
+            TestASTNode::LETSTATEMENT,
+            TestASTNode::BINARY,
+            TestASTNode::BINARY,
+            TestASTNode::BINARY,
+            TestASTNode::VAR("a".to_string()),
+            TestASTNode::NUMBER(12),
+            TestASTNode::NUMBER(7),
+            TestASTNode::BOOLEAN(false),
+        ];
+
+        assert_ast(input, expected);
+    }
+
+    #[test]
+    fn should_fold_short_circuited_operands() {
+        let input = "func f() -> Bool { return true } let a = !(1 > 2) || f(); let b = (1 == 2) && f()";
+        let expected = vec![
+            TestASTNode::BLOCK,
+            TestASTNode::BOOLEAN(true),
+            TestASTNode::LETSTATEMENT,
+            TestASTNode::BOOLEAN(true),
+            TestASTNode::LETSTATEMENT,
+            TestASTNode::BOOLEAN(false),
+        ];
+
+        assert_ast(input, expected);
+    }
 }
\ No newline at end of file
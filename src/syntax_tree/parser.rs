@@ -1,7 +1,8 @@
 use std::cell::Cell;
+use std::rc::Rc;
 
-use crate::syntax_tree::{BinaryOperator, BinaryOperatorType, Expression, ASTStatement, UnaryOperator, UnaryOperatorType, lexer::{Token, TokenType}};
-use crate::diagnostics::DiagnosticsVectorCell;
+use crate::syntax_tree::{Associativity, BinaryOperator, BinaryOperatorType, Expression, ExpressionType, ASTStatement, ASTElseStatement, ASTParameter, InterpolationPart, UnaryOperator, UnaryOperatorType, lexer::{Lexer, SourceCodeSpan, StringPart, Token, TokenType, decode_string}};
+use crate::diagnostics::{DiagnosticsVector, DiagnosticsVectorCell};
 
 pub struct CompileTimeCounter {
     value: Cell<usize>,
@@ -24,21 +25,61 @@ impl CompileTimeCounter {
 
 pub struct Parser {
     tokens: Vec<Token>,
+    //Whether a line break separates each token from the previous one
+    starts_line: Vec<bool>,
+    //Number of enclosing parentheses, a line break inside them never ends a statement
+    parenthesis_depth: usize,
     current: CompileTimeCounter,
     diagnostics_vec: DiagnosticsVectorCell,
+    //Set by the first syntax error of a statement until the parser synchronizes at the next statement
+    panicking: Cell<bool>,
 }
 
 impl Parser {
     pub fn new( tokens: Vec<Token>, diagnostics_vec: DiagnosticsVectorCell ) -> Self {
+        let (tokens, starts_line) = Self::attach_comments(tokens, &diagnostics_vec);
         Self {
-            tokens: tokens.iter()
-            .filter( |token| token.kind != TokenType::WHITESPACE )
-            .map(|token| token.clone()).collect(),
+            tokens,
+            starts_line,
+            parenthesis_depth: 0,
             current: CompileTimeCounter::new(),
             diagnostics_vec,
+            panicking: Cell::new(false),
         }
     }
 
+    //Drops the trivia, a comment on the same line as the previous token trails it and any other comment
+    //leads the next token
+    fn attach_comments(tokens: Vec<Token>, diagnostics_vec: &DiagnosticsVectorCell) -> (Vec<Token>, Vec<bool>) {
+        let mut significant_tokens: Vec<Token> = Vec::new();
+        let mut starts_line = Vec::new();
+        let mut pending_comments = Vec::new();
+        let mut on_previous_line = false;
+        for mut token in tokens {
+            match token.kind {
+                TokenType::WHITESPACE => on_previous_line &= !token.span.literal.contains('\n'),
+                TokenType::COMMENT | TokenType::UNTERMINATEDCOMMENT => {
+                    if token.kind == TokenType::UNTERMINATEDCOMMENT {
+                        diagnostics_vec.borrow_mut().report_unterminated_comment(&token);
+                    }
+                    let spans_lines = token.span.literal.contains('\n');
+                    match significant_tokens.last_mut() {
+                        Some(previous) if on_previous_line && pending_comments.is_empty() => previous.trailing_comments.push(token),
+                        _ => pending_comments.push(token),
+                    }
+                    on_previous_line &= !spans_lines;
+                }
+                _ => {
+                    token.leading_comments = std::mem::take(&mut pending_comments);
+                    starts_line.push(!on_previous_line);
+                    significant_tokens.push(token);
+                    on_previous_line = true;
+                }
+            }
+        }
+        return (significant_tokens, starts_line);
+    }
+
     pub fn next_statement(&mut self) -> Option<ASTStatement> {
         if self.is_at_end() {
             return None;
@@ -46,24 +87,205 @@ impl Parser {
         return Some(self.parse_statement());
     }
 
+    //Comments after the last statement, only complete once every statement was parsed
+    pub fn end_of_file_comments(&mut self) -> Vec<Token> {
+        return self.tokens.last_mut().map(|token| std::mem::take(&mut token.leading_comments)).unwrap_or_default();
+    }
+
     fn is_at_end(&self) -> bool {
         return self.current_token().kind == TokenType::EOF;
     }
 
     fn parse_statement(&mut self) -> ASTStatement {
+        let start = self.current.get_value();
+        let reported_errors = self.diagnostics_vec.borrow().diagnostics.len();
+        let mut statement = self.parse_statement_kind();
+        let has_errors = self.diagnostics_vec.borrow().diagnostics.len() > reported_errors;
+        if self.panicking.get() {
+            self.synchronize();
+        } else {
+            self.parse_statement_end(has_errors);
+        }
+        //A token that cannot start a statement is skipped so parsing always moves on
+        if self.current.get_value() == start {
+            self.consume();
+        }
+        let end = self.current.get_value().clamp(start + 1, self.tokens.len());
+        //Nested statements took their comments already, what is left inside moves in front of the statement
+        for index in start..end {
+            let token = &mut self.tokens[index];
+            statement.leading_comments.append(&mut token.leading_comments);
+            if index + 1 < end {
+                statement.leading_comments.append(&mut token.trailing_comments);
+            }
+        }
+        statement.trailing_comments = std::mem::take(&mut self.tokens[end - 1].trailing_comments);
+        return statement;
+    }
+
+    //A statement ends with an optional ";", the end of its line, the end of the enclosing block or its own closing brace,
+    //a statement with errors or an invalid token that follows are already reported on their own
+    fn parse_statement_end(&mut self, has_errors: bool) {
+        if self.current_token().kind == TokenType::SEMICOLON {
+            self.consume();
+            return;
+        }
+        let next = self.current_token();
+        let is_separated = has_errors
+            || self.starts_line[self.current_index()]
+            || matches!(next.kind, TokenType::RIGHTBRACE | TokenType::EOF | TokenType::INVALID)
+            || self.peek(-1).kind == TokenType::RIGHTBRACE;
+        if !is_separated {
+            self.diagnostics_vec.borrow_mut().report_missing_separator(next);
+        }
+    }
+
+    //Skips the rest of a statement with a syntax error up to the next ";", line, statement keyword or the end of the
+    //enclosing block, nested blocks are skipped as a whole
+    fn synchronize(&mut self) {
+        let mut depth = 0;
+        loop {
+            let token = self.current_token();
+            let at_boundary = depth == 0 && (self.starts_line[self.current_index()] || Self::is_statement_keyword(&token.kind));
+            match token.kind {
+                TokenType::EOF => break,
+                TokenType::RIGHTBRACE if depth == 0 => break,
+                _ if at_boundary => break,
+                TokenType::SEMICOLON if depth == 0 => {
+                    self.consume();
+                    break;
+                }
+                TokenType::LEFTBRACE => depth += 1,
+                TokenType::RIGHTBRACE => depth -= 1,
+                _ => {}
+            }
+            self.consume();
+        }
+        self.panicking.set(false);
+    }
+
+    fn is_statement_keyword(kind: &TokenType) -> bool {
+        return matches!(kind, TokenType::LET | TokenType::VAR | TokenType::IF | TokenType::WHILE | TokenType::BREAK | TokenType::CONTINUE | TokenType::FUNC | TokenType::RETURN);
+    }
+
+    //Tokens that open or close a construct are left to the construct they belong to instead of being consumed by an error
+    fn is_structural(kind: &TokenType) -> bool {
+        return Self::is_statement_keyword(kind) || matches!(kind,
+            TokenType::ELSE | TokenType::LEFTBRACE | TokenType::RIGHTBRACE | TokenType::RIGHTPAR | TokenType::SEMICOLON | TokenType::EOF
+        );
+    }
+
+    fn parse_statement_kind(&mut self) -> ASTStatement {
         match self.current_token().kind {
-            TokenType::LET => return self.parse_let_statement(),
+            TokenType::LET | TokenType::VAR => return self.parse_let_statement(),
+            TokenType::LEFTBRACE => return self.parse_block_statement(),
+            TokenType::IF => return self.parse_if_statement(),
+            TokenType::WHILE => return self.parse_while_statement(),
+            TokenType::BREAK => return ASTStatement::break_statement(self.consume().clone()),
+            TokenType::CONTINUE => return ASTStatement::continue_statement(self.consume().clone()),
+            TokenType::FUNC => return self.parse_function_declaration(),
+            TokenType::RETURN => return self.parse_return_statement(),
 
             _ => return self.parse_expression_statement()
         }
     }
 
+    fn parse_if_statement(&mut self) -> ASTStatement {
+        let if_keyword = self.consume_with_check(TokenType::IF).clone();
+        let condition = self.parse_expression();
+        let then_branch = self.parse_block_statement();
+        let else_branch = self.parse_optional_else_statement();
+        return ASTStatement::if_statement(if_keyword, condition, then_branch, else_branch);
+    }
+
+    fn parse_while_statement(&mut self) -> ASTStatement {
+        let while_keyword = self.consume_with_check(TokenType::WHILE).clone();
+        let condition = self.parse_expression();
+        let body = self.parse_block_statement();
+        return ASTStatement::while_statement(while_keyword, condition, body);
+    }
+
+    fn parse_function_declaration(&mut self) -> ASTStatement {
+        let func_keyword = self.consume_with_check(TokenType::FUNC).clone();
+        let identifier = self.consume_with_check(TokenType::IDENTIFIER).clone();
+        self.consume_with_check(TokenType::LEFTPAR);
+        let mut parameters = Vec::new();
+        while self.current_token().kind != TokenType::RIGHTPAR && !self.is_at_end() && !self.panicking.get() {
+            let parameter = self.consume_with_check(TokenType::IDENTIFIER).clone();
+            let type_annotation = self.parse_optional_type_annotation(TokenType::COLON);
+            parameters.push(ASTParameter::new(parameter, type_annotation));
+            if self.current_token().kind != TokenType::RIGHTPAR {
+                self.consume_with_check(TokenType::COMMA);
+            }
+        }
+        self.consume_with_check(TokenType::RIGHTPAR);
+        let return_type = self.parse_optional_type_annotation(TokenType::ARROW);
+        let body = self.parse_block_statement();
+        return ASTStatement::function_declaration(func_keyword, identifier, parameters, return_type, body);
+    }
+
+    //Parses "<separator> TypeName" if the separator is present
+    fn parse_optional_type_annotation(&mut self, separator: TokenType) -> Option<Token> {
+        if self.current_token().kind != separator {
+            return None;
+        }
+        self.consume();
+        return Some(self.consume_with_check(TokenType::IDENTIFIER).clone());
+    }
+
+    fn parse_return_statement(&mut self) -> ASTStatement {
+        let return_keyword = self.consume_with_check(TokenType::RETURN).clone();
+        //A bare "return" ends where the statement ends, the return value has to start on the same line
+        let return_value = match self.current_token().kind {
+            TokenType::RIGHTBRACE | TokenType::EOF | TokenType::SEMICOLON => None,
+            _ if self.starts_line[self.current_index()] => None,
+            _ => Some(self.parse_expression()),
+        };
+        return ASTStatement::return_statement(return_keyword, return_value);
+    }
+
+    fn parse_optional_else_statement(&mut self) -> Option<ASTElseStatement> {
+        if self.current_token().kind != TokenType::ELSE {
+            return None;
+        }
+        let else_keyword = self.consume_with_check(TokenType::ELSE).clone();
+        let else_statement = if self.current_token().kind == TokenType::IF {
+            self.parse_if_statement()
+        } else {
+            self.parse_block_statement()
+        };
+        return Some(ASTElseStatement::new(else_keyword, else_statement));
+    }
+
+    fn parse_block_statement(&mut self) -> ASTStatement {
+        //Without its opening brace the block is left empty, synchronizing skips what was meant to be its body
+        if self.current_token().kind != TokenType::LEFTBRACE {
+            self.consume_with_check(TokenType::LEFTBRACE);
+            return ASTStatement::block_statement(Vec::new(), Vec::new());
+        }
+        self.consume_with_check(TokenType::LEFTBRACE);
+        //A comment after the opening brace goes with the first statement of the block
+        let opening_brace = self.current.get_value().min(self.tokens.len()) - 1;
+        let opening_comments = std::mem::take(&mut self.tokens[opening_brace].trailing_comments);
+        let index = self.current_index();
+        self.tokens[index].leading_comments.splice(0..0, opening_comments);
+        let mut statements = Vec::new();
+        while self.current_token().kind != TokenType::RIGHTBRACE && !self.is_at_end() {
+            statements.push(self.parse_statement());
+        }
+        let index = self.current_index();
+        let closing_comments = std::mem::take(&mut self.tokens[index].leading_comments);
+        self.consume_with_check(TokenType::RIGHTBRACE);
+        return ASTStatement::block_statement(statements, closing_comments);
+    }
+
     fn parse_let_statement(&mut self) -> ASTStatement {
-        self.consume_with_check(TokenType::LET);
+        let keyword = self.consume().clone();
         let identifier = self.consume_with_check(TokenType::IDENTIFIER).clone();
+        let type_annotation = self.parse_optional_type_annotation(TokenType::COLON);
         self.consume_with_check(TokenType::EQUALS);
         let expr = self.parse_expression();
-        return ASTStatement::let_statement(identifier, expr);
+        return ASTStatement::let_statement(keyword, identifier, type_annotation, expr);
     }
 
     fn parse_expression_statement(&mut self) -> ASTStatement {
@@ -72,7 +294,43 @@ impl Parser {
     }
 
     fn parse_expression(&mut self) -> Expression {
-        return self.parse_binary_expression(0);
+        return self.parse_assignment_expression();
+    }
+
+    //Assignments have the lowest precedence and group to the right, so "a = b = 1" assigns 1 to both
+    fn parse_assignment_expression(&mut self) -> Expression {
+        let target = self.parse_binary_expression(0);
+        let Some(operator) = Self::assignment_operator(&self.current_token().kind) else {
+            return target;
+        };
+        let operator_token = self.consume().clone();
+        let value = self.parse_assignment_expression();
+        return match target.kind {
+            ExpressionType::VARIABLE(variable) => Expression::assignment(variable.identifier, operator_token, operator, value),
+            _ => {
+                self.report_syntax_error(|diagnostics| diagnostics.report_invalid_assignment_target(&operator_token));
+                Expression::error(operator_token.span.clone())
+            }
+        };
+    }
+
+    //None is a valid assignment operator, it stands for a plain "="
+    fn assignment_operator(kind: &TokenType) -> Option<Option<BinaryOperatorType>> {
+        return match kind {
+            TokenType::EQUALS => Some(None),
+            TokenType::PLUSEQUALS => Some(Some(BinaryOperatorType::PLUS)),
+            TokenType::MINUSEQUALS => Some(Some(BinaryOperatorType::MINUS)),
+            TokenType::ASTERISKEQUALS => Some(Some(BinaryOperatorType::MULTIPLY)),
+            TokenType::SLASHEQUALS => Some(Some(BinaryOperatorType::DIVIDE)),
+            TokenType::PERCENTEQUALS => Some(Some(BinaryOperatorType::MODULO)),
+            TokenType::POWEREQUALS => Some(Some(BinaryOperatorType::POWER)),
+            TokenType::SHIFTLEFTEQUALS => Some(Some(BinaryOperatorType::SHIFTLEFT)),
+            TokenType::SHIFTRIGHTEQUALS => Some(Some(BinaryOperatorType::SHIFTRIGHT)),
+            TokenType::AMPERSANDEQUALS => Some(Some(BinaryOperatorType::AND)),
+            TokenType::PIPEEQUALS => Some(Some(BinaryOperatorType::OR)),
+            TokenType::CARETEQUALS => Some(Some(BinaryOperatorType::XOR)),
+            _ => None,
+        };
     }
 
     fn parse_binary_expression(&mut self, precedence: u8) -> Expression {
@@ -83,8 +341,17 @@ impl Parser {
             if operator_precedence < precedence {
                 break;
             }
+            //"-" at the start of a line begins a new statement with a negation instead of continuing this one
+            if operator.kind == BinaryOperatorType::MINUS && self.starts_new_line() {
+                break;
+            }
             self.consume();
-            let right = self.parse_binary_expression(operator_precedence);
+            //The right operand only takes operators of the same precedence when they group to the right
+            let right_precedence = match operator.associativity() {
+                Associativity::LEFT => operator_precedence + 1,
+                Associativity::RIGHT => operator_precedence,
+            };
+            let right = self.parse_binary_expression(right_precedence);
             left = Expression::binary(operator, left, right);
         }
 
@@ -94,17 +361,35 @@ impl Parser {
     fn parse_unary_expression(&mut self) -> Expression {
         if let Some(operator) = self.parse_unary_operator() {
             self.consume();
-            let operand = self.parse_unary_expression();
+            if let Some(minimum) = self.parse_minimum_integer(&operator) {
+                return minimum;
+            }
+            //Prefix operators bind tighter than every binary operator except "**", so "-2 ** 2" is "-(2 ** 2)"
+            let operand = self.parse_binary_expression(BinaryOperatorType::POWER.precedence());
             return Expression::unary(operator, operand);
         }
         return self.parse_primary_expression();
     }
 
+    //"-9223372036854775808" is the only way to write i64::MIN, its magnitude alone is one too large, unless it is
+    //raised to a power first
+    fn parse_minimum_integer(&mut self, operator: &UnaryOperator) -> Option<Expression> {
+        let token = self.current_token();
+        if operator.kind != UnaryOperatorType::MINUS || token.kind != TokenType::OVERFLOWINGNUMERAL || self.peek(1).kind == TokenType::POWER {
+            return None;
+        }
+        let number = Lexer::negated_integer(&token.span.literal)?;
+        let literal = format!("-{}", token.span.literal);
+        self.consume();
+        return Some(Expression::number_literal(number, literal));
+    }
+
     fn parse_unary_operator(&mut self) -> Option<UnaryOperator> {
         let token = self.current_token();
         let kind = match token.kind {
             TokenType::MINUS => Some(UnaryOperatorType::MINUS),
             TokenType::NOT => Some(UnaryOperatorType::NOT),
+            TokenType::BANG => Some(UnaryOperatorType::LOGICALNOT),
             _ => None
             
         };
@@ -118,31 +403,78 @@ impl Parser {
             TokenType::MINUS => Some(BinaryOperatorType::MINUS),
             TokenType::ASTERISK => Some(BinaryOperatorType::MULTIPLY), 
             TokenType::SLASH => Some(BinaryOperatorType::DIVIDE),
+            TokenType::PERCENT => Some(BinaryOperatorType::MODULO),
+            TokenType::SHIFTLEFT => Some(BinaryOperatorType::SHIFTLEFT),
+            TokenType::SHIFTRIGHT => Some(BinaryOperatorType::SHIFTRIGHT),
             TokenType::AMPERSAND => Some(BinaryOperatorType::AND),
             TokenType::PIPE => Some(BinaryOperatorType::OR),
             TokenType::CARET => Some(BinaryOperatorType::XOR),
             TokenType::POWER => Some(BinaryOperatorType::POWER),
+            TokenType::EQUALSEQUALS => Some(BinaryOperatorType::EQUALS),
+            TokenType::BANGEQUALS => Some(BinaryOperatorType::NOTEQUALS),
+            TokenType::LESSTHAN => Some(BinaryOperatorType::LESSTHAN),
+            TokenType::LESSTHANEQUALS => Some(BinaryOperatorType::LESSTHANOREQUAL),
+            TokenType::GREATERTHAN => Some(BinaryOperatorType::GREATERTHAN),
+            TokenType::GREATERTHANEQUALS => Some(BinaryOperatorType::GREATERTHANOREQUAL),
+            TokenType::DOUBLEAMPERSAND => Some(BinaryOperatorType::LOGICALAND),
+            TokenType::DOUBLEPIPE => Some(BinaryOperatorType::LOGICALOR),
             _ => None 
         };
         return kind.map(|kind| BinaryOperator::new(kind, token.clone()));
     }
 
     fn parse_primary_expression(&mut self) -> Expression {
+        if Self::is_structural(&self.current_token().kind) {
+            let token = self.current_token();
+            self.report_syntax_error(|diagnostics| diagnostics.report_expected_expression(token));
+            return Expression::error(token.span.clone());
+        }
         let token = self.consume();
         return match token.kind {
             TokenType::NUMERAL(number) => {
-                Expression::number(number)
+                Expression::number_literal(number, token.span.literal.clone())
+            }
+            TokenType::DOUBLE(value) => {
+                Expression::double_literal(value, token.span.literal.clone())
+            }
+            //The literal is still an expression, so the parser does not need to recover from it
+            TokenType::OVERFLOWINGNUMERAL | TokenType::OVERFLOWINGDOUBLE | TokenType::INVALIDNUMERAL => {
+                let mut diagnostics = self.diagnostics_vec.borrow_mut();
+                match token.kind {
+                    TokenType::OVERFLOWINGNUMERAL => diagnostics.report_overflowing_numeral(token),
+                    TokenType::OVERFLOWINGDOUBLE => diagnostics.report_overflowing_double(token),
+                    _ => diagnostics.report_invalid_numeral(token),
+                }
+                Expression::error(token.span.clone())
+            }
+            TokenType::TRUE => {
+                Expression::boolean(true)
+            }
+            TokenType::FALSE => {
+                Expression::boolean(false)
+            }
+            TokenType::STRING | TokenType::UNTERMINATEDSTRING => {
+                let token = token.clone();
+                self.parse_string(&token)
             }
             TokenType::LEFTPAR => {
+                self.parenthesis_depth += 1;
                 let expr = self.parse_expression();
+                self.parenthesis_depth -= 1;
                 self.consume_with_check(TokenType::RIGHTPAR);
                 Expression::parenthesized(expr)
             }
             TokenType::IDENTIFIER => {
-                Expression::identifier(token.clone())
+                let identifier = token.clone();
+                //A "(" on the next line starts a parenthesized statement rather than a call
+                if self.current_token().kind == TokenType::LEFTPAR && !self.starts_new_line() {
+                    let arguments = self.parse_call_arguments();
+                    return Expression::call(identifier, arguments);
+                }
+                Expression::identifier(identifier)
             }
             _ => {
-                self.diagnostics_vec.borrow_mut().report_expected_expression(token);
+                self.report_syntax_error(|diagnostics| diagnostics.report_expected_expression(token));
                 Expression::error(
                     token.span.clone()
                 )
@@ -150,6 +482,64 @@ impl Parser {
         };
     }
 
+    //Problems inside a literal do not throw the parser off, so they are reported even while panicking
+    fn parse_string(&self, token: &Token) -> Expression {
+        let (parts, invalid_escapes) = decode_string(token);
+        {
+            let mut diagnostics = self.diagnostics_vec.borrow_mut();
+            if token.kind == TokenType::UNTERMINATEDSTRING {
+                diagnostics.report_unterminated_string(token);
+            }
+            for span in invalid_escapes {
+                diagnostics.report_invalid_escape(span);
+            }
+        }
+        if !parts.iter().any(|part| matches!(part, StringPart::INTERPOLATION(_))) {
+            let value = parts.into_iter().map(|part| match part {
+                StringPart::TEXT(text) => text,
+                StringPart::INTERPOLATION(_) => String::new(),
+            }).collect();
+            return Expression::string(value);
+        }
+        let parts = parts.into_iter().map(|part| match part {
+            StringPart::TEXT(text) => InterpolationPart::TEXT(text),
+            StringPart::INTERPOLATION(span) => InterpolationPart::EXPRESSION(Box::new(self.parse_interpolation(&span)), span),
+        }).collect();
+        return Expression::interpolation(parts);
+    }
+
+    //The expression is lexed and parsed on its own along with the closing parenthesis, which is what a missing
+    //expression is reported at, its tokens are moved to where they are in the source
+    fn parse_interpolation(&self, span: &SourceCodeSpan) -> Expression {
+        let source = format!("{})", span.literal);
+        let mut lexer = Lexer::new(&source);
+        let mut tokens = Vec::new();
+        while let Some(mut token) = lexer.next_token() {
+            token.span.start += span.start;
+            token.span.end += span.start;
+            tokens.push(token);
+        }
+        let mut parser = Parser::new(tokens, Rc::clone(&self.diagnostics_vec));
+        let expression = parser.parse_expression();
+        parser.consume_with_check(TokenType::RIGHTPAR);
+        return expression;
+    }
+
+    fn parse_call_arguments(&mut self) -> Vec<Expression> {
+        self.consume_with_check(TokenType::LEFTPAR);
+        self.parenthesis_depth += 1;
+        let mut arguments = Vec::new();
+        while self.current_token().kind != TokenType::RIGHTPAR && !self.is_at_end() && !self.panicking.get() {
+            arguments.push(self.parse_expression());
+            if self.current_token().kind != TokenType::RIGHTPAR {
+                self.consume_with_check(TokenType::COMMA);
+            }
+        }
+        self.parenthesis_depth -= 1;
+        self.consume_with_check(TokenType::RIGHTPAR);
+        return arguments;
+    }
+
     fn peek(&self, offset: isize) -> &Token {
         let mut index = (self.current.get_value() as isize + offset) as usize;
         if index >= self.tokens.len() {
@@ -161,17 +551,30 @@ impl Parser {
 
     fn current_token(&self) -> &Token { return self.peek(0); }
 
+    fn current_index(&self) -> usize { return self.current.get_value().min(self.tokens.len() - 1); }
+
+    //Whether the current token could begin the next statement, which it cannot inside parentheses
+    fn starts_new_line(&self) -> bool { return self.parenthesis_depth == 0 && self.starts_line[self.current_index()]; }
+
     fn consume(&self) -> &Token {
         self.current.add(1);
         return self.peek(-1);
     }
 
+    //A missing token is reported and treated as if it was there, the unexpected token is left for what comes next
     fn consume_with_check(&self, kind: TokenType) -> &Token {
-        let token = self.consume();
-        if token.kind != kind {
-            self.diagnostics_vec.borrow_mut().report_unexpected_token(&kind, token);
+        if self.current_token().kind == kind {
+            return self.consume();
         }
-
+        let token = self.current_token();
+        self.report_syntax_error(|diagnostics| diagnostics.report_unexpected_token(&kind, token));
         return token;
     }
+
+    //Only the first syntax error of a statement is reported, the following ones are usually caused by it
+    fn report_syntax_error(&self, report: impl FnOnce(&mut DiagnosticsVector)) {
+        if !self.panicking.replace(true) {
+            report(&mut self.diagnostics_vec.borrow_mut());
+        }
+    }
 }
\ No newline at end of file
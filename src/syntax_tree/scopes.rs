@@ -0,0 +1,46 @@
+use std::collections::HashMap;
+
+//Stack of lexical scopes, the last element is the innermost one
+#[derive(Clone)]
+pub struct ScopeStack<T> {
+    scopes: Vec<HashMap<String, T>>,
+}
+
+impl<T> ScopeStack<T> {
+    pub fn new() -> Self {
+        Self { scopes: vec![HashMap::new()] }
+    }
+
+    pub fn enter_scope(&mut self) { self.scopes.push(HashMap::new()); }
+
+    pub fn exit_scope(&mut self) {
+        //The global scope lives as long as the stack itself
+        if self.scopes.len() > 1 {
+            self.scopes.pop();
+        }
+    }
+
+    pub fn is_global(&self) -> bool { return self.scopes.len() == 1; }
+
+    //Detaches every local scope so a called function only sees the globals and its own frame
+    pub fn take_locals(&mut self) -> Vec<HashMap<String, T>> {
+        return self.scopes.split_off(1);
+    }
+
+    pub fn restore_locals(&mut self, locals: Vec<HashMap<String, T>>) {
+        self.scopes.truncate(1);
+        self.scopes.extend(locals);
+    }
+
+    pub fn declare(&mut self, identifier: String, value: T) {
+        self.scopes.last_mut().unwrap().insert(identifier, value);
+    }
+
+    pub fn lookup(&self, identifier: &str) -> Option<&T> {
+        return self.scopes.iter().rev().find_map(|scope| scope.get(identifier));
+    }
+
+    pub fn lookup_mut(&mut self, identifier: &str) -> Option<&mut T> {
+        return self.scopes.iter_mut().rev().find_map(|scope| scope.get_mut(identifier));
+    }
+}
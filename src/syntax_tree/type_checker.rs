@@ -0,0 +1,355 @@
+use std::collections::HashMap;
+use std::fmt::{Display, Formatter};
+use crate::syntax_tree::{AbstractSyntaxTree, ASTStatement, ASTStatementType, AssignmentExpression, BinaryExpression, BinaryOperatorType, BooleanExpression, ASTLetStatement, ASTIfStatement, ASTWhileStatement, ASTBlockStatement, ASTFunctionDeclaration, ASTReturnStatement, CallExpression, DoubleExpression, Expression, ExpressionType, InterpolationExpression, InterpolationPart, NumberExpression, StringExpression, UnaryExpression, UnaryOperatorType, VariableExpression, ASTTraverser};
+use crate::syntax_tree::evaluator::BuiltinFunction;
+use crate::syntax_tree::lexer::{SourceCodeSpan, Token};
+use crate::syntax_tree::scopes::ScopeStack;
+use crate::diagnostics::DiagnosticsVectorCell;
+
+#[derive(Debug, PartialEq, Clone, Copy)]
+pub enum Type {
+    INT,
+    DOUBLE,
+    BOOL,
+    STRING,
+    VOID,
+    //Type of unannotated parameters and return values, only checked at runtime
+    UNKNOWN,
+}
+
+impl Type {
+    pub fn from_name(name: &str) -> Option<Type> {
+        return match name {
+            "Int" => Some(Type::INT),
+            "Double" => Some(Type::DOUBLE),
+            "Bool" => Some(Type::BOOL),
+            "String" => Some(Type::STRING),
+            "Void" => Some(Type::VOID),
+            _ => None,
+        };
+    }
+
+    pub fn is_assignable_to(&self, other: &Type) -> bool {
+        return *self == Type::UNKNOWN || *other == Type::UNKNOWN || self == other;
+    }
+}
+
+impl Display for Type {
+    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+        match self {
+            Type::INT => write!(f, "Int"),
+            Type::DOUBLE => write!(f, "Double"),
+            Type::BOOL => write!(f, "Bool"),
+            Type::STRING => write!(f, "String"),
+            Type::VOID => write!(f, "Void"),
+            Type::UNKNOWN => write!(f, "Unknown"),
+        }
+    }
+}
+
+#[derive(Clone)]
+struct FunctionSignature {
+    parameters: Vec<Type>,
+    return_type: Type,
+}
+
+#[derive(Clone)]
+pub struct TypeChecker {
+    diagnostics: DiagnosticsVectorCell,
+    variables: ScopeStack<Type>,
+    functions: HashMap<String, FunctionSignature>,
+    //Declared return type of the function whose body is being checked
+    return_type: Option<Type>,
+    last_type: Type,
+    //Types of the variables and parameters declared in the source code by the start of their identifier
+    declared_types: HashMap<usize, Type>,
+}
+
+impl TypeChecker {
+    pub fn new(diagnostics: DiagnosticsVectorCell) -> Self {
+        Self {
+            diagnostics,
+            variables: ScopeStack::new(),
+            functions: HashMap::new(),
+            return_type: None,
+            last_type: Type::VOID,
+            declared_types: HashMap::new(),
+        }
+    }
+
+    pub fn declare_functions(&mut self, ast: &AbstractSyntaxTree) {
+        for function in ast.functions() {
+            let parameters = function.parameters.iter()
+                .map(|parameter| self.resolve_annotation(&parameter.type_annotation))
+                .collect();
+            let return_type = self.resolve_annotation(&function.return_type);
+            self.functions.insert(function.identifier().to_string(), FunctionSignature { parameters, return_type });
+        }
+    }
+
+    pub fn declare_variable(&mut self, name: &str, value_type: Type) {
+        self.variables.declare(name.to_string(), value_type);
+    }
+
+    pub fn declare_function(&mut self, name: &str, parameters: Vec<Type>, return_type: Type) {
+        self.functions.insert(name.to_string(), FunctionSignature { parameters, return_type });
+    }
+
+    pub fn declared_type(&self, identifier: &SourceCodeSpan) -> Option<Type> {
+        return self.declared_types.get(&identifier.start).copied();
+    }
+
+    fn resolve_annotation(&mut self, type_annotation: &Option<Token>) -> Type {
+        let Some(type_name) = type_annotation else { return Type::UNKNOWN; };
+        return match Type::from_name(&type_name.span.literal) {
+            Some(resolved) => resolved,
+            None => {
+                self.diagnostics.borrow_mut().report_unknown_type(type_name);
+                Type::UNKNOWN
+            }
+        };
+    }
+
+    fn check_expression(&mut self, expression: &Expression) -> Type {
+        self.goto_expression(expression);
+        return self.last_type;
+    }
+
+    fn expect_type(&mut self, expected: Type, actual: Type, span: &SourceCodeSpan) {
+        if !actual.is_assignable_to(&expected) {
+            self.diagnostics.borrow_mut().report_type_mismatch(&expected, &actual, span);
+        }
+    }
+
+    //An operator with two mismatched operands is reported once, at the first of them
+    fn expect_operand_types(&mut self, expected: Type, left: Type, right: Type, span: &SourceCodeSpan) {
+        let mismatched = if left.is_assignable_to(&expected) { right } else { left };
+        self.expect_type(expected, mismatched, span);
+    }
+
+    //Integers and doubles are never converted implicitly, so a double operand makes the operation a double one,
+    //two unknown operands leave it to the runtime
+    fn numeric_operand_type(left: Type, right: Type) -> Type {
+        if left == Type::DOUBLE || right == Type::DOUBLE {
+            return Type::DOUBLE;
+        }
+        if left == Type::UNKNOWN && right == Type::UNKNOWN {
+            return Type::UNKNOWN;
+        }
+        return Type::INT;
+    }
+
+    //Whether running the statement always ends in a return, loops only count when they can never be left
+    fn always_returns(statement: &ASTStatement) -> bool {
+        return match &statement.kind {
+            ASTStatementType::RETURN(_) => true,
+            ASTStatementType::BLOCK(block_statement) => block_statement.statements.iter().any(Self::always_returns),
+            ASTStatementType::IF(if_statement) => match &if_statement.else_branch {
+                Some(else_branch) => Self::always_returns(&if_statement.then_branch) && Self::always_returns(&else_branch.else_statement),
+                None => false,
+            },
+            ASTStatementType::WHILE(while_statement) => {
+                matches!(&while_statement.condition.kind, ExpressionType::BOOLEAN(condition) if condition.value)
+                    && !Self::breaks_out(&while_statement.body)
+            }
+            _ => false,
+        };
+    }
+
+    //Whether the statement contains a "break" of the loop it is in, breaks of nested loops do not count
+    fn breaks_out(statement: &ASTStatement) -> bool {
+        return match &statement.kind {
+            ASTStatementType::BREAK(_) => true,
+            ASTStatementType::BLOCK(block_statement) => block_statement.statements.iter().any(Self::breaks_out),
+            ASTStatementType::IF(if_statement) => {
+                Self::breaks_out(&if_statement.then_branch)
+                    || if_statement.else_branch.as_ref().is_some_and(|else_branch| Self::breaks_out(&else_branch.else_statement))
+            }
+            _ => false,
+        };
+    }
+
+    //Checks the operand types and returns the type of the result
+    fn check_binary_operation(&mut self, operator: BinaryOperatorType, left: Type, right: Type, span: &SourceCodeSpan) -> Type {
+        return match operator {
+            BinaryOperatorType::EQUALS | BinaryOperatorType::NOTEQUALS => {
+                self.expect_type(left, right, span);
+                Type::BOOL
+            }
+            BinaryOperatorType::LOGICALAND | BinaryOperatorType::LOGICALOR => {
+                self.expect_operand_types(Type::BOOL, left, right, span);
+                Type::BOOL
+            }
+            BinaryOperatorType::LESSTHAN | BinaryOperatorType::LESSTHANOREQUAL | BinaryOperatorType::GREATERTHAN | BinaryOperatorType::GREATERTHANOREQUAL => {
+                let operand_type = Self::numeric_operand_type(left, right);
+                self.expect_operand_types(operand_type, left, right, span);
+                Type::BOOL
+            }
+            //Either operand being a string makes "+" a concatenation
+            BinaryOperatorType::PLUS if left == Type::STRING || right == Type::STRING => {
+                self.expect_operand_types(Type::STRING, left, right, span);
+                Type::STRING
+            }
+            BinaryOperatorType::PLUS | BinaryOperatorType::MINUS | BinaryOperatorType::MULTIPLY | BinaryOperatorType::DIVIDE | BinaryOperatorType::MODULO | BinaryOperatorType::POWER => {
+                let operand_type = Self::numeric_operand_type(left, right);
+                self.expect_operand_types(operand_type, left, right, span);
+                operand_type
+            }
+            //Bitwise operators only exist for integers
+            _ => {
+                self.expect_operand_types(Type::INT, left, right, span);
+                Type::INT
+            }
+        };
+    }
+}
+
+impl ASTTraverser for TypeChecker {
+    fn goto_let_statement(&mut self, let_statement: &ASTLetStatement) {
+        let initializer_type = self.check_expression(&let_statement.initializer);
+        let declared_type = match &let_statement.type_annotation {
+            Some(_) => {
+                let annotated_type = self.resolve_annotation(&let_statement.type_annotation);
+                self.expect_type(annotated_type, initializer_type, &let_statement.identifier.span);
+                annotated_type
+            }
+            None => initializer_type,
+        };
+        self.declared_types.insert(let_statement.identifier.span.start, declared_type);
+        self.variables.declare(let_statement.identifier.span.literal.clone(), declared_type);
+    }
+
+    fn goto_block_statement(&mut self, block_statement: &ASTBlockStatement) {
+        self.variables.enter_scope();
+        for statement in &block_statement.statements {
+            self.goto_statement(statement);
+        }
+        self.variables.exit_scope();
+    }
+
+    fn goto_if_statement(&mut self, if_statement: &ASTIfStatement) {
+        let condition_type = self.check_expression(&if_statement.condition);
+        self.expect_type(Type::BOOL, condition_type, &if_statement.if_keyword.span);
+        self.goto_statement(&if_statement.then_branch);
+        if let Some(else_branch) = &if_statement.else_branch {
+            self.goto_statement(&else_branch.else_statement);
+        }
+    }
+
+    fn goto_while_statement(&mut self, while_statement: &ASTWhileStatement) {
+        let condition_type = self.check_expression(&while_statement.condition);
+        self.expect_type(Type::BOOL, condition_type, &while_statement.while_keyword.span);
+        self.goto_statement(&while_statement.body);
+    }
+
+    fn goto_function_declaration(&mut self, function_declaration: &ASTFunctionDeclaration) {
+        let Some(signature) = self.functions.get(function_declaration.identifier()) else { return; };
+        let parameter_types = signature.parameters.clone();
+        let signature_return_type = signature.return_type;
+        let enclosing_return_type = self.return_type.replace(signature_return_type);
+        self.variables.enter_scope();
+        for (parameter, parameter_type) in function_declaration.parameters.iter().zip(parameter_types) {
+            self.declared_types.insert(parameter.identifier.span.start, parameter_type);
+            self.variables.declare(parameter.identifier.span.literal.clone(), parameter_type);
+        }
+        self.goto_statement(&function_declaration.body);
+        let needs_value = !matches!(signature_return_type, Type::VOID | Type::UNKNOWN);
+        if needs_value && !Self::always_returns(&function_declaration.body) {
+            self.diagnostics.borrow_mut().report_missing_return(&function_declaration.identifier);
+        }
+        self.variables.exit_scope();
+        self.return_type = enclosing_return_type;
+    }
+
+    fn goto_return_statement(&mut self, return_statement: &ASTReturnStatement) {
+        let value_type = match &return_statement.return_value {
+            Some(return_value) => self.check_expression(return_value),
+            None => Type::VOID,
+        };
+        if let Some(return_type) = self.return_type {
+            self.expect_type(return_type, value_type, &return_statement.return_keyword.span);
+        }
+    }
+
+    fn goto_call_expression(&mut self, call_expression: &CallExpression) {
+        let argument_types: Vec<Type> = call_expression.arguments.iter()
+            .map(|argument| self.check_expression(argument))
+            .collect();
+        //Builtins print values of any type and do not return anything
+        if BuiltinFunction::from_name(call_expression.identifier()).is_some() {
+            self.last_type = Type::VOID;
+            return;
+        }
+        let Some(signature) = self.functions.get(call_expression.identifier()) else {
+            self.last_type = Type::UNKNOWN;
+            return;
+        };
+        let return_type = signature.return_type;
+        for (parameter_type, argument_type) in signature.parameters.clone().into_iter().zip(argument_types) {
+            self.expect_type(parameter_type, argument_type, &call_expression.identifier.span);
+        }
+        self.last_type = return_type;
+    }
+
+    fn goto_variable_expression(&mut self, variable_expression: &VariableExpression) {
+        self.last_type = self.variables.lookup(variable_expression.identifier()).copied().unwrap_or(Type::UNKNOWN);
+    }
+
+    fn goto_assignment_expression(&mut self, assignment_expression: &AssignmentExpression) {
+        let value_type = self.check_expression(&assignment_expression.value);
+        let variable_type = self.variables.lookup(assignment_expression.identifier()).copied().unwrap_or(Type::UNKNOWN);
+        let span = &assignment_expression.operator_token.span;
+        let assigned_type = match assignment_expression.operator {
+            Some(operator) => self.check_binary_operation(operator, variable_type, value_type, span),
+            None => value_type,
+        };
+        self.expect_type(variable_type, assigned_type, span);
+        self.last_type = variable_type;
+    }
+
+    fn goto_number_expression(&mut self, number: &NumberExpression) {
+        self.last_type = Type::INT;
+    }
+
+    fn goto_double_expression(&mut self, double: &DoubleExpression) {
+        self.last_type = Type::DOUBLE;
+    }
+
+    fn goto_boolean_expression(&mut self, boolean: &BooleanExpression) {
+        self.last_type = Type::BOOL;
+    }
+
+    fn goto_string_expression(&mut self, string: &StringExpression) {
+        self.last_type = Type::STRING;
+    }
+
+    fn goto_interpolation_expression(&mut self, interpolation: &InterpolationExpression) {
+        for part in &interpolation.parts {
+            if let InterpolationPart::EXPRESSION(expression, _) = part {
+                self.check_expression(expression);
+            }
+        }
+        self.last_type = Type::STRING;
+    }
+
+    fn goto_error(&mut self, span: &SourceCodeSpan) {
+        self.last_type = Type::UNKNOWN;
+    }
+
+    fn goto_unary_expression(&mut self, unary_expression: &UnaryExpression) {
+        let operand_type = self.check_expression(&unary_expression.operand);
+        let expected = match unary_expression.operator.kind {
+            UnaryOperatorType::MINUS if operand_type == Type::DOUBLE || operand_type == Type::UNKNOWN => operand_type,
+            UnaryOperatorType::MINUS | UnaryOperatorType::NOT => Type::INT,
+            UnaryOperatorType::LOGICALNOT => Type::BOOL,
+        };
+        self.expect_type(expected, operand_type, &unary_expression.operator.token.span);
+        self.last_type = expected;
+    }
+
+    fn goto_binary_expression(&mut self, binary_expression: &BinaryExpression) {
+        let left = self.check_expression(&binary_expression.left);
+        let right = self.check_expression(&binary_expression.right);
+        self.last_type = self.check_binary_operation(binary_expression.operator.kind, left, right, &binary_expression.operator.token.span);
+    }
+}
@@ -0,0 +1,119 @@
+use std::fmt::{Display, Formatter};
+use crate::syntax_tree::type_checker::Type;
+
+#[derive(Debug, PartialEq, Clone)]
+pub enum Value {
+    INTEGER(i64),
+    DOUBLE(f64),
+    BOOLEAN(bool),
+    STRING(String),
+}
+
+impl Value {
+    pub fn type_name(&self) -> &'static str {
+        match self {
+            Value::INTEGER(_) => "Int",
+            Value::DOUBLE(_) => "Double",
+            Value::BOOLEAN(_) => "Bool",
+            Value::STRING(_) => "String",
+        }
+    }
+
+    pub fn value_type(&self) -> Type {
+        match self {
+            Value::INTEGER(_) => Type::INT,
+            Value::DOUBLE(_) => Type::DOUBLE,
+            Value::BOOLEAN(_) => Type::BOOL,
+            Value::STRING(_) => Type::STRING,
+        }
+    }
+}
+
+impl From<i64> for Value {
+    fn from(value: i64) -> Self { Value::INTEGER(value) }
+}
+
+impl From<f64> for Value {
+    fn from(value: f64) -> Self { Value::DOUBLE(value) }
+}
+
+impl From<bool> for Value {
+    fn from(value: bool) -> Self { Value::BOOLEAN(value) }
+}
+
+impl From<String> for Value {
+    fn from(value: String) -> Self { Value::STRING(value) }
+}
+
+impl From<&str> for Value {
+    fn from(value: &str) -> Self { Value::STRING(value.to_string()) }
+}
+
+//Rust types the result of a script can be converted into
+pub trait FromValue: Sized {
+    const TYPE: Type;
+
+    fn from_value(value: Value) -> Option<Self>;
+}
+
+impl FromValue for i64 {
+    const TYPE: Type = Type::INT;
+
+    fn from_value(value: Value) -> Option<Self> {
+        match value {
+            Value::INTEGER(value) => Some(value),
+            _ => None,
+        }
+    }
+}
+
+impl FromValue for f64 {
+    const TYPE: Type = Type::DOUBLE;
+
+    fn from_value(value: Value) -> Option<Self> {
+        match value {
+            Value::DOUBLE(value) => Some(value),
+            _ => None,
+        }
+    }
+}
+
+impl FromValue for bool {
+    const TYPE: Type = Type::BOOL;
+
+    fn from_value(value: Value) -> Option<Self> {
+        match value {
+            Value::BOOLEAN(value) => Some(value),
+            _ => None,
+        }
+    }
+}
+
+impl FromValue for String {
+    const TYPE: Type = Type::STRING;
+
+    fn from_value(value: Value) -> Option<Self> {
+        match value {
+            Value::STRING(value) => Some(value),
+            _ => None,
+        }
+    }
+}
+
+impl FromValue for Value {
+    const TYPE: Type = Type::UNKNOWN;
+
+    fn from_value(value: Value) -> Option<Self> { Some(value) }
+}
+
+impl Display for Value {
+    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+        match self {
+            Value::INTEGER(value) => write!(f, "{}", value),
+            //Debug formatting keeps the fraction of whole numbers, so 2.0 is not shown as the integer 2
+            Value::DOUBLE(value) => write!(f, "{:?}", value),
+            Value::BOOLEAN(value) => write!(f, "{}", value),
+            Value::STRING(value) => write!(f, "{}", value),
+        }
+    }
+}